@@ -0,0 +1,58 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::{ast::ZetaFrontmatter, compiler::QiitaFrontmatter};
+
+/// Slugs of the articles in `zeta/`, sorted.
+pub fn slugs() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("zeta") else {
+        return Vec::new();
+    };
+    let mut slugs: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "md" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    slugs.sort();
+    slugs
+}
+
+/// Reads the frontmatter of a zeta article without scanning its body.
+pub fn read_zeta_frontmatter(slug: &str) -> Option<ZetaFrontmatter> {
+    let file = fs::read_to_string(format!("zeta/{}.md", slug)).ok()?;
+    let file = file.trim_start().strip_prefix("---\n")?;
+    let end = file.find("---\n")?;
+    serde_yaml::from_str(&file[..end]).ok()
+}
+
+/// Reads the frontmatter of an article already compiled to `public/`.
+/// `Ok(None)` if it hasn't been compiled yet.
+pub fn read_qiita_frontmatter(slug: &str) -> Result<Option<QiitaFrontmatter>, String> {
+    let path = format!("public/{}.md", slug);
+    let Ok(existing_file) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let existing_header = existing_file
+        .strip_prefix("---")
+        .and_then(|file| file.find("\n---").map(|end| &file[..end]))
+        .ok_or_else(|| format!("{} has no frontmatter", path))?;
+    let de = serde_yaml::Deserializer::from_str(existing_header);
+    QiitaFrontmatter::deserialize(de)
+        .map(Some)
+        .map_err(|error| format!("Failed to parse the frontmatter of {}: {}", path, error))
+}
+
+/// Resolves the Qiita item id of an article from `public/` or its zeta frontmatter.
+pub fn resolve_qiita_id(slug: &str, frontmatter: &ZetaFrontmatter) -> Option<String> {
+    read_qiita_frontmatter(slug)
+        .ok()
+        .flatten()
+        .and_then(|existing_fm| existing_fm.id)
+        .filter(|id| !id.is_empty())
+        .or_else(|| frontmatter.qiita_id.clone().filter(|id| !id.is_empty()))
+}
@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
+
 use crate::{
-    r#macro::{ParsedMacro, Platform},
+    r#macro::{ParsedMacro, Platform, StringMacro},
     token::Token,
 };
 
@@ -25,20 +27,112 @@ pub type ParsedMd = MarkdownDoc<ZetaFrontmatter, Element>;
 pub struct ZetaFrontmatter {
     pub title: String,
     pub emoji: String,
-    pub r#type: String,
+    pub r#type: ArticleType,
     pub topics: Vec<String>,
     pub qiita_id: Option<String>,
     pub published: bool,
+    /// reserved publishing time in JST (`YYYY-MM-DD HH:MM`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
     /// compile only specified platform
     #[serde(skip_serializing_if = "Option::is_none")]
     pub only: Option<Platform>,
+    /// named macros referenced with `{{name}}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub macros: BTreeMap<String, StringMacro>,
+    /// insert a table of contents at the top of the Qiita article
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub toc: bool,
+    /// multi-part series the article belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<Series>,
+    /// overrides applied only to Zenn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zenn: Option<ZennOverrides>,
+    /// overrides applied only to Qiita
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qiita: Option<QiitaOverrides>,
+}
+
+impl ZetaFrontmatter {
+    /// Applies the `zenn` overrides.
+    pub fn for_zenn(mut self) -> Self {
+        if let Some(overrides) = self.zenn.clone() {
+            self.title = overrides.title.unwrap_or(self.title);
+            self.topics = overrides.topics.unwrap_or(self.topics);
+            self.published = overrides.published.unwrap_or(self.published);
+        }
+        self
+    }
+
+    /// Applies the `qiita` overrides.
+    pub fn for_qiita(mut self) -> Self {
+        if let Some(overrides) = self.qiita.clone() {
+            self.title = overrides.title.unwrap_or(self.title);
+            self.topics = overrides.tags.unwrap_or(self.topics);
+            self.published = overrides.published.unwrap_or(self.published);
+        }
+        self
+    }
+}
+
+/// `series: { name: ..., order: 1 }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Series {
+    pub name: String,
+    pub order: usize,
+}
+
+/// tech: 技術記事 / idea: アイデア
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleType {
+    #[default]
+    Tech,
+    Idea,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZennOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QiitaOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// case-sensitive Qiita tags used instead of `topics`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<bool>,
+    /// Qiita Organization to publish the article under
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization_url_name: Option<String>,
+    /// publish as a slide deck
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slide: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Element {
     Text(String),
+    /// source text that only carries layout, like list markers and emphasis delimiters
+    Trivia(String),
     Url(String),
     Macro(ParsedMacro),
+    Variable(Variable),
     LinkCard {
         card_type: String,
         url: String,
@@ -46,9 +140,21 @@ pub enum Element {
     Image {
         alt: String,
         url: String,
+        width: Option<usize>,
+        caption: Option<String>,
+    },
+    /// link to another zeta article
+    ArticleLink {
+        text: Vec<Element>,
+        slug: String,
+        qiita_id: Option<String>,
     },
-    InlineFootnote(String),
+    InlineFootnote(Vec<Element>),
     Footnote(String),
+    FootnoteDefinition {
+        name: String,
+        body: Vec<Element>,
+    },
     Message {
         level: usize,
         msg_type: MessageType,
@@ -59,6 +165,160 @@ pub enum Element {
         title: String,
         body: Vec<Element>,
     },
+    Mermaid {
+        code: String,
+        /// the block as written, with the fences
+        source: String,
+    },
+    Heading {
+        level: usize,
+        text: Vec<Element>,
+    },
+    /// table of contents
+    Toc,
+    /// page separator of a slide deck
+    SlideBreak {
+        title: Option<String>,
+    },
+    /// included only in the listed platforms
+    Conditional {
+        platforms: Vec<Platform>,
+        body: Vec<Element>,
+    },
+    Paragraph(Vec<Element>),
+    /// `> quote`
+    BlockQuote(Vec<Element>),
+    /// `- item` or `1. item`
+    List {
+        /// number of the first item of an ordered list
+        start: Option<usize>,
+        /// bullet (`-`, `*`, `+`) or delimiter of an ordered list (`.`, `)`)
+        marker: char,
+        /// items starting with their markers as trivia
+        items: Vec<Vec<Element>>,
+    },
+    /// fenced or indented code as written, with the fences
+    CodeBlock(String),
+    /// cells keep the pipes, the delimiter row and the line breaks as trivia
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<Vec<Element>>,
+        rows: Vec<Vec<Vec<Element>>>,
+    },
+    /// `---`, `***` or `___` as written
+    ThematicBreak(String),
+    /// raw HTML block
+    Html(Vec<Element>),
+    Emphasis(Vec<Element>),
+    Strong(Vec<Element>),
+    /// `~~text~~`
+    Strikethrough(Vec<Element>),
+    InlineCode {
+        code: String,
+        /// number of the backticks around the code
+        fence: usize,
+    },
+    Link {
+        text: Vec<Element>,
+        url: String,
+        title: Option<String>,
+    },
+}
+
+impl Element {
+    /// Child elements of the CommonMark nodes, for walkers that look through the document structure.
+    pub fn markdown_children(&self) -> Vec<&Vec<Element>> {
+        match self {
+            Element::Paragraph(children)
+            | Element::BlockQuote(children)
+            | Element::Html(children)
+            | Element::Emphasis(children)
+            | Element::Strong(children)
+            | Element::Strikethrough(children)
+            | Element::Link { text: children, .. }
+            | Element::Heading { text: children, .. } => vec![children],
+            Element::List { items, .. } => items.iter().collect(),
+            Element::Table { header, rows, .. } => header.iter().chain(rows.iter().flatten()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Rebuilds the CommonMark nodes with their children mapped by `f`.
+    pub fn map_markdown_children(self, mut f: impl FnMut(Vec<Element>) -> Vec<Element>) -> Element {
+        match self {
+            Element::Paragraph(children) => Element::Paragraph(f(children)),
+            Element::BlockQuote(children) => Element::BlockQuote(f(children)),
+            Element::Html(children) => Element::Html(f(children)),
+            Element::Emphasis(children) => Element::Emphasis(f(children)),
+            Element::Strong(children) => Element::Strong(f(children)),
+            Element::Strikethrough(children) => Element::Strikethrough(f(children)),
+            Element::Link { text, url, title } => Element::Link {
+                text: f(text),
+                url,
+                title,
+            },
+            Element::Heading { level, text } => Element::Heading {
+                level,
+                text: f(text),
+            },
+            Element::List {
+                start,
+                marker,
+                items,
+            } => Element::List {
+                start,
+                marker,
+                items: items.into_iter().map(&mut f).collect(),
+            },
+            Element::Table {
+                alignments,
+                header,
+                rows,
+            } => Element::Table {
+                alignments,
+                header: header.into_iter().map(&mut f).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(&mut f).collect())
+                    .collect(),
+            },
+            element => element,
+        }
+    }
+}
+
+/// column alignment of a GFM table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// `{{name}}` expanded by each backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Title,
+    Emoji,
+    Topics,
+    Repository,
+    ZennUrl,
+    QiitaUrl,
+}
+
+impl Variable {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "title" => Some(Variable::Title),
+            "emoji" => Some(Variable::Emoji),
+            "topics" => Some(Variable::Topics),
+            "repository" => Some(Variable::Repository),
+            "zenn_url" => Some(Variable::ZennUrl),
+            "qiita_url" => Some(Variable::QiitaUrl),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,3 +327,47 @@ pub enum MessageType {
     Warn,
     Alert,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontmatter(yaml: &str) -> ZetaFrontmatter {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn overrides_apply_to_their_platform() {
+        let shared = "title: Shared\nemoji: 📝\ntype: tech\ntopics: [rust]\npublished: false\n";
+        let overridden = frontmatter(&format!(
+            "{}zenn:\n  title: Zenn\n  published: true\nqiita:\n  tags: [Rust]\n",
+            shared
+        ));
+
+        let zenn = overridden.clone().for_zenn();
+        assert_eq!(zenn.title, "Zenn");
+        assert_eq!(zenn.topics, ["rust"]);
+        assert!(zenn.published);
+
+        let qiita = overridden.for_qiita();
+        assert_eq!(qiita.title, "Shared");
+        assert_eq!(qiita.topics, ["Rust"]);
+        assert!(!qiita.published);
+
+        let plain = frontmatter(shared);
+        assert_eq!(plain.clone().for_zenn().title, "Shared");
+        assert_eq!(plain.for_qiita().topics, ["rust"]);
+    }
+
+    #[test]
+    fn unknown_override_keys_are_errors() {
+        let shared = "title: a\nemoji: 📝\ntype: tech\ntopics: []\npublished: false\n";
+        for overrides in ["zenn:\n  tittle: a\n", "qiita:\n  topics: [a]\n"] {
+            let error =
+                serde_yaml::from_str::<ZetaFrontmatter>(&format!("{}{}", shared, overrides))
+                    .unwrap_err();
+            assert!(error.to_string().contains("unknown field"), "{}", error);
+            assert_eq!(error.location().unwrap().line(), 7);
+        }
+    }
+}
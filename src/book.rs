@@ -0,0 +1,307 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, DirBuilder},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    article,
+    ast::{ParsedMd, ZetaFrontmatter},
+    checker,
+    compiler::ZennCompiler,
+    load_macros, load_settings, mermaid, parser,
+    print::{zeta_error, zeta_message},
+    r#macro::{Platform, StringMacro},
+    report_parse_errors, scan,
+    series::{self, Part},
+    Settings,
+};
+
+/// cover image names accepted by Zenn
+const BOOK_COVERS: [&str; 4] = ["cover.png", "cover.jpg", "cover.jpeg", "cover.webp"];
+
+/// `zeta/books/<slug>/config.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookConfig {
+    pub title: String,
+    pub summary: String,
+    pub topics: Vec<String>,
+    pub published: bool,
+    pub price: usize,
+    /// chapter file names without `.md`, in reading order
+    pub chapters: Vec<String>,
+    /// also compile the chapters to Qiita as a series of linked articles
+    #[serde(default)]
+    pub qiita: bool,
+}
+
+/// `books/<slug>/config.yaml` read by Zenn CLI
+#[derive(Debug, Serialize)]
+struct ZennBookConfig {
+    title: String,
+    summary: String,
+    topics: Vec<String>,
+    published: bool,
+    price: usize,
+    chapters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChapterFrontmatter {
+    pub title: String,
+    /// readable without purchasing the book
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free: Option<bool>,
+}
+
+#[derive(Clone)]
+struct Chapter {
+    name: String,
+    frontmatter: ChapterFrontmatter,
+    file: ParsedMd,
+}
+
+pub fn new_book(slug: &str) {
+    let dir = format!("zeta/books/{}", slug);
+    if Path::new(&dir).exists() {
+        zeta_error("Book already exists");
+        return;
+    }
+    DirBuilder::new().recursive(true).create(&dir).unwrap();
+
+    let config = BookConfig {
+        chapters: vec!["introduction".to_string()],
+        ..Default::default()
+    };
+    fs::write(
+        format!("{}/config.yaml", dir),
+        serde_yaml::to_string(&config).unwrap(),
+    )
+    .unwrap();
+
+    let chapter = ChapterFrontmatter::default();
+    fs::write(
+        format!("{}/introduction.md", dir),
+        format!("---\n{}---\n", serde_yaml::to_string(&chapter).unwrap()),
+    )
+    .unwrap();
+}
+
+/// Builds a book and its Qiita series. Returns whether every output was written.
+pub fn build_book(slug: &str, render_mermaid: &[Platform]) -> bool {
+    let Ok(config) = fs::read_to_string(format!("zeta/books/{}/config.yaml", slug)) else {
+        zeta_error("Book not found");
+        return false;
+    };
+    let config: BookConfig = match serde_yaml::from_str(&config) {
+        Ok(config) => config,
+        Err(error) => {
+            zeta_error(&format!("Failed to parse config.yaml: {}", error));
+            return false;
+        }
+    };
+    if let Err(errors) = checker::check_zenn_book(&config, &chapter_files(slug)) {
+        errors
+            .iter()
+            .for_each(|error| zeta_error(&format!("config.yaml: {}", error)));
+        return false;
+    }
+    let Some(macros) = load_macros() else {
+        return false;
+    };
+
+    let mut chapters = Vec::new();
+    for name in &config.chapters {
+        let Some(chapter) = load_chapter(slug, name, &config, &macros) else {
+            return false;
+        };
+        chapters.push(chapter);
+    }
+
+    let render_zenn = render_mermaid.contains(&Platform::Zenn);
+    let render_qiita = config.qiita && render_mermaid.contains(&Platform::Qiita);
+    let rendered = (render_zenn || render_qiita).then(|| render_chapters(slug, &chapters));
+
+    let settings = load_settings();
+    let mut ok = match &rendered {
+        Some(rendered) if render_zenn => compile_zenn_book(slug, &config, rendered, &settings),
+        _ => compile_zenn_book(slug, &config, &chapters, &settings),
+    };
+    if config.qiita {
+        let chapters = match rendered {
+            Some(rendered) if render_qiita => rendered,
+            _ => chapters,
+        };
+        ok &= compile_qiita_series(slug, &config, chapters);
+    }
+    ok
+}
+
+/// Names of the chapter files of a book, without `.md`.
+fn chapter_files(slug: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(format!("zeta/books/{}", slug)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "md" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect()
+}
+
+fn render_chapters(slug: &str, chapters: &[Chapter]) -> Vec<Chapter> {
+    chapters
+        .iter()
+        .cloned()
+        .map(|mut chapter| {
+            chapter.file = mermaid::render_mermaid(chapter.file, &series_slug(slug, &chapter.name));
+            chapter
+        })
+        .collect()
+}
+
+fn load_chapter(
+    slug: &str,
+    name: &str,
+    config: &BookConfig,
+    macros: &BTreeMap<String, StringMacro>,
+) -> Option<Chapter> {
+    let Ok(file) = fs::read_to_string(format!("zeta/books/{}/{}.md", slug, name)) else {
+        zeta_error(&format!("Chapter not found: {}", name));
+        return None;
+    };
+    let markdown = scan(&file)?;
+    let frontmatter: ChapterFrontmatter = match serde_yaml::from_str(&markdown.frontmatter) {
+        Ok(frontmatter) => frontmatter,
+        Err(error) => {
+            zeta_error(&format!("Failed to parse the frontmatter of {}: {}", name, error));
+            return None;
+        }
+    };
+
+    let article_frontmatter = ZetaFrontmatter {
+        title: frontmatter.title.clone(),
+        topics: config.topics.clone(),
+        published: config.published,
+        ..Default::default()
+    };
+    let parser = parser::Parser::new(markdown).with_macros(macros.clone());
+    let file = report_parse_errors(parser.parse_with(article_frontmatter))?;
+
+    Some(Chapter {
+        name: name.to_string(),
+        frontmatter,
+        file,
+    })
+}
+
+fn compile_zenn_book(
+    slug: &str,
+    config: &BookConfig,
+    chapters: &[Chapter],
+    settings: &Settings,
+) -> bool {
+    let mut ok = true;
+    for chapter in chapters {
+        if let Err(errors) = checker::check_zenn_chapter(&chapter.file) {
+            errors
+                .iter()
+                .for_each(|error| zeta_error(&format!("{}: {}", chapter.name, error)));
+            ok = false;
+        }
+    }
+    if !ok {
+        return false;
+    }
+
+    let mut bodies = Vec::new();
+    for chapter in chapters {
+        let compiler = ZennCompiler::new(&chapter.name, settings.clone());
+        match compiler.compile_body(chapter.file.clone()) {
+            Ok(body) => bodies.push(body),
+            Err(errors) => {
+                errors
+                    .iter()
+                    .for_each(|error| zeta_error(&format!("{}: {}", chapter.name, error)));
+                ok = false;
+            }
+        }
+    }
+    if !ok {
+        return false;
+    }
+
+    let dir = format!("books/{}", slug);
+    DirBuilder::new().recursive(true).create(&dir).unwrap();
+
+    let zenn_config = ZennBookConfig {
+        title: config.title.clone(),
+        summary: config.summary.clone(),
+        topics: config.topics.clone(),
+        published: config.published,
+        price: config.price,
+        chapters: config.chapters.clone(),
+    };
+    fs::write(
+        format!("{}/config.yaml", dir),
+        serde_yaml::to_string(&zenn_config).unwrap(),
+    )
+    .unwrap();
+
+    for (chapter, body) in chapters.iter().zip(bodies) {
+        let header = serde_yaml::to_string(&chapter.frontmatter).unwrap();
+        fs::write(
+            format!("{}/{}.md", dir, chapter.name),
+            format!("---\n{}---\n{}", header, body),
+        )
+        .unwrap();
+    }
+
+    for cover in BOOK_COVERS {
+        let source = format!("zeta/books/{}/{}", slug, cover);
+        if Path::new(&source).exists() {
+            fs::copy(&source, format!("{}/{}", dir, cover)).unwrap();
+        }
+    }
+
+    zeta_message(&format!("Compiled {} chapters to {}", chapters.len(), dir));
+    true
+}
+
+/// Compiles every chapter to `public/<book>-<chapter>.md` with links to the other parts.
+fn compile_qiita_series(slug: &str, config: &BookConfig, chapters: Vec<Chapter>) -> bool {
+    let parts: Vec<Part> = chapters
+        .iter()
+        .map(|chapter| {
+            let slug = series_slug(slug, &chapter.name);
+            let qiita_id = article::resolve_qiita_id(&slug, &ZetaFrontmatter::default());
+            Part {
+                slug,
+                title: chapter.frontmatter.title.clone(),
+                qiita_id,
+            }
+        })
+        .collect();
+
+    let mut ok = true;
+    for mut chapter in chapters {
+        let chapter_slug = series_slug(slug, &chapter.name);
+        let navigation =
+            series::parts_navigation(&config.title, &parts, &chapter_slug, Platform::Qiita);
+        chapter.file.frontmatter.title = format!("{}: {}", config.title, chapter.frontmatter.title);
+        chapter.file.elements.extend(navigation);
+        ok &= crate::compile_qiita(chapter.file, &chapter_slug, &series::Index::default());
+    }
+    ok
+}
+
+/// name of the Qiita article of a chapter
+fn series_slug(slug: &str, chapter: &str) -> String {
+    format!("{}-{}", slug, chapter)
+}
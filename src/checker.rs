@@ -0,0 +1,661 @@
+use std::fmt::Display;
+
+use crate::{
+    ast::{Element, ParsedMd, ZetaFrontmatter},
+    book::BookConfig,
+    compiler::{self, QiitaFrontmatter},
+    r#macro::Platform,
+    schedule, Settings,
+};
+
+/// Zenn rejects mermaid blocks longer than this.
+const ZENN_MERMAID_CHARS_MAX: usize = 2000;
+/// Zenn rejects mermaid blocks with more `&` chains than this.
+const ZENN_MERMAID_CHAINS_MAX: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckError {
+    MermaidTooLong { index: usize, chars: usize },
+    MermaidTooManyChains { index: usize, chains: usize },
+    ArticleWithoutQiitaId(String),
+    InvalidZennSlug(String),
+    InvalidEmoji(String),
+    InvalidZennTopic(String),
+    TooManyTopics(Vec<String>),
+    ZennTitleTooLong(usize),
+    InvalidPublishedAt(String),
+    InvalidPublicationName(String),
+    InvalidQiitaTagCount(usize),
+    InvalidQiitaTag(String),
+    EmptyQiitaTitle,
+    QiitaTitleTooLong(usize),
+    InvalidQiitaId(String),
+    InvalidOrganizationName(String),
+    InvalidBookPrice(usize),
+    InvalidChapterSlug(String),
+    /// chapter listed in config.yaml without a file
+    MissingChapter(String),
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::MermaidTooLong { index, chars } => write!(
+                f,
+                "Mermaid diagram #{} has {} characters. Zenn allows at most {}.",
+                index, chars, ZENN_MERMAID_CHARS_MAX
+            ),
+            CheckError::MermaidTooManyChains { index, chains } => write!(
+                f,
+                "Mermaid diagram #{} has {} chains ('&'). Zenn allows at most {}.",
+                index, chains, ZENN_MERMAID_CHAINS_MAX
+            ),
+            CheckError::InvalidZennSlug(slug) => write!(
+                f,
+                "Invalid slug '{}'. Zenn requires {}-{} characters of [a-z0-9_-].",
+                slug, ZENN_SLUG_MIN, ZENN_SLUG_MAX
+            ),
+            CheckError::InvalidEmoji(emoji) => {
+                write!(f, "Invalid emoji '{}'. Specify exactly one emoji.", emoji)
+            }
+            CheckError::InvalidZennTopic(topic) => write!(
+                f,
+                "Invalid topic '{}'. Zenn topics must be lowercase alphanumerics.",
+                topic
+            ),
+            CheckError::TooManyTopics(topics) => write!(
+                f,
+                "Too many topics: [{}]. The maximum number of topics is {}.",
+                topics.join(", "),
+                TOPICS_MAX
+            ),
+            CheckError::ZennTitleTooLong(len) => write!(
+                f,
+                "The title has {} characters. Zenn allows at most {}.",
+                len, ZENN_TITLE_MAX
+            ),
+            CheckError::InvalidPublicationName(name) => write!(
+                f,
+                "Invalid publication_name '{}'. Expected lowercase alphanumerics, '_' and '-'.",
+                name
+            ),
+            CheckError::InvalidPublishedAt(published_at) => write!(
+                f,
+                "Invalid published_at '{}'. Expected 'YYYY-MM-DD HH:MM'.",
+                published_at
+            ),
+            CheckError::InvalidQiitaTagCount(count) => write!(
+                f,
+                "The article has {} tags. Qiita requires 1 to {} tags.",
+                count, TOPICS_MAX
+            ),
+            CheckError::InvalidQiitaTag(tag) => write!(
+                f,
+                "Invalid tag '{}'. Qiita tags must not contain spaces or commas.",
+                tag
+            ),
+            CheckError::EmptyQiitaTitle => write!(f, "The title is empty. Qiita requires a title."),
+            CheckError::QiitaTitleTooLong(len) => write!(
+                f,
+                "The title has {} characters. Qiita allows at most {}.",
+                len, QIITA_TITLE_MAX
+            ),
+            CheckError::InvalidQiitaId(id) => write!(
+                f,
+                "Invalid Qiita id '{}'. Expected {} hexadecimal characters.",
+                id, QIITA_ID_LEN
+            ),
+            CheckError::InvalidOrganizationName(name) => write!(
+                f,
+                "Invalid organization_url_name '{}'. Expected alphanumerics and hyphens.",
+                name
+            ),
+            CheckError::InvalidBookPrice(price) => write!(
+                f,
+                "Invalid price {}. Zenn requires 0 or {} to {} in units of {}.",
+                price, ZENN_BOOK_PRICE_MIN, ZENN_BOOK_PRICE_MAX, ZENN_BOOK_PRICE_UNIT
+            ),
+            CheckError::InvalidChapterSlug(slug) => write!(
+                f,
+                "Invalid chapter '{}'. Zenn requires 1-{} characters of [a-z0-9_-].",
+                slug, ZENN_CHAPTER_SLUG_MAX
+            ),
+            CheckError::MissingChapter(slug) => {
+                write!(f, "Chapter '{0}' is listed but {0}.md is not found.", slug)
+            }
+            CheckError::ArticleWithoutQiitaId(slug) => write!(
+                f,
+                "Linked article '{}' has no Qiita id yet. Publish it on Qiita first.",
+                slug
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+const ZENN_SLUG_MIN: usize = 12;
+const ZENN_SLUG_MAX: usize = 50;
+const ZENN_TITLE_MAX: usize = 70;
+const TOPICS_MAX: usize = 5;
+const QIITA_TITLE_MAX: usize = 255;
+const QIITA_ID_LEN: usize = 20;
+const ZENN_CHAPTER_SLUG_MAX: usize = 50;
+/// Paid books cost 200 to 5000 yen in units of 100 yen.
+const ZENN_BOOK_PRICE_MIN: usize = 200;
+const ZENN_BOOK_PRICE_MAX: usize = 5000;
+const ZENN_BOOK_PRICE_UNIT: usize = 100;
+
+/// Maximum edit distance for an unmapped topic to be reported as a misspelling.
+const TOPIC_TYPO_DISTANCE_MAX: usize = 2;
+
+/// Checks the article against the limits of Zenn.
+pub fn check_zenn(
+    file: &ParsedMd,
+    slug: &str,
+    settings: &Settings,
+) -> Result<(), Vec<CheckError>> {
+    let mut errors = Vec::new();
+    let frontmatter = file.frontmatter.clone().for_zenn();
+    check_zenn_frontmatter(&frontmatter, slug, &mut errors);
+    check_publication(&frontmatter, settings, &mut errors);
+    let mut mermaid_index = 0;
+    check_zenn_elements(&file.elements, &mut mermaid_index, &mut errors);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// Checks the config of a Zenn book. `chapter_files` are the chapter files without `.md`.
+pub fn check_zenn_book(
+    config: &BookConfig,
+    chapter_files: &[String],
+) -> Result<(), Vec<CheckError>> {
+    let mut errors = Vec::new();
+    let paid = (ZENN_BOOK_PRICE_MIN..=ZENN_BOOK_PRICE_MAX).contains(&config.price)
+        && config.price.is_multiple_of(ZENN_BOOK_PRICE_UNIT);
+    if config.price != 0 && !paid {
+        errors.push(CheckError::InvalidBookPrice(config.price));
+    }
+    check_zenn_topics(&config.topics, &mut errors);
+
+    for chapter in &config.chapters {
+        if !(1..=ZENN_CHAPTER_SLUG_MAX).contains(&chapter.chars().count())
+            || !chapter.chars().all(is_zenn_slug_char)
+        {
+            errors.push(CheckError::InvalidChapterSlug(chapter.clone()));
+        } else if !chapter_files.contains(chapter) {
+            errors.push(CheckError::MissingChapter(chapter.clone()));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// Checks a chapter of a Zenn book, which has no article frontmatter.
+pub fn check_zenn_chapter(file: &ParsedMd) -> Result<(), Vec<CheckError>> {
+    let mut errors = Vec::new();
+    let mut mermaid_index = 0;
+    check_zenn_elements(&file.elements, &mut mermaid_index, &mut errors);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+fn check_zenn_frontmatter(frontmatter: &ZetaFrontmatter, slug: &str, errors: &mut Vec<CheckError>) {
+    let slug_len = slug.chars().count();
+    if !(ZENN_SLUG_MIN..=ZENN_SLUG_MAX).contains(&slug_len) || !slug.chars().all(is_zenn_slug_char)
+    {
+        errors.push(CheckError::InvalidZennSlug(slug.to_string()));
+    }
+
+    if !is_single_emoji(&frontmatter.emoji) {
+        errors.push(CheckError::InvalidEmoji(frontmatter.emoji.clone()));
+    }
+
+    check_zenn_topics(&frontmatter.topics, errors);
+
+    let title_len = frontmatter.title.chars().count();
+    if title_len > ZENN_TITLE_MAX {
+        errors.push(CheckError::ZennTitleTooLong(title_len));
+    }
+
+    check_published_at(frontmatter, errors);
+}
+
+fn check_zenn_topics(topics: &[String], errors: &mut Vec<CheckError>) {
+    if topics.len() > TOPICS_MAX {
+        errors.push(CheckError::TooManyTopics(topics.to_vec()));
+    }
+    for topic in topics {
+        if topic.is_empty()
+            || !topic
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        {
+            errors.push(CheckError::InvalidZennTopic(topic.clone()));
+        }
+    }
+}
+
+/// Characters of article and chapter slugs.
+fn is_zenn_slug_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'
+}
+
+fn check_publication(
+    frontmatter: &ZetaFrontmatter,
+    settings: &Settings,
+    errors: &mut Vec<CheckError>,
+) {
+    let Some(name) = compiler::publication_name(frontmatter, settings) else {
+        return;
+    };
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        errors.push(CheckError::InvalidPublicationName(name));
+    }
+}
+
+fn check_published_at(frontmatter: &ZetaFrontmatter, errors: &mut Vec<CheckError>) {
+    if let Some(published_at) = &frontmatter.published_at {
+        if schedule::parse_published_at(published_at).is_none() {
+            errors.push(CheckError::InvalidPublishedAt(published_at.clone()));
+        }
+    }
+}
+
+/// Whether the string is a single emoji, including ZWJ sequences, flags and modifiers.
+fn is_single_emoji(emoji: &str) -> bool {
+    if emoji.is_ascii() {
+        return false;
+    }
+
+    let mut count = 0;
+    let mut joined = false;
+    let mut regional_indicators = 0;
+
+    for c in emoji.chars() {
+        match c {
+            '\u{200D}' => joined = true,
+            // variation selectors, keycap, skin tones and tags
+            '\u{FE0E}'
+            | '\u{FE0F}'
+            | '\u{20E3}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}' => (),
+            '\u{1F1E6}'..='\u{1F1FF}' => {
+                regional_indicators += 1;
+                if regional_indicators % 2 == 1 && !joined {
+                    count += 1;
+                }
+                joined = false;
+            }
+            c if is_emoji_base(c) || (is_keycap_base(c) && emoji.contains('\u{20E3}')) => {
+                if !joined {
+                    count += 1;
+                }
+                joined = false;
+            }
+            _ => return false,
+        }
+    }
+
+    count == 1
+}
+
+/// Code points that are displayed as an emoji by themselves or with `U+FE0F`.
+fn is_emoji_base(c: char) -> bool {
+    matches!(
+        c,
+        '\u{A9}'
+            | '\u{AE}'
+            | '\u{203C}'
+            | '\u{2049}'
+            | '\u{2122}'
+            | '\u{2139}'
+            | '\u{2194}'..='\u{21AA}'
+            | '\u{231A}'..='\u{23FF}'
+            | '\u{24C2}'
+            | '\u{25AA}'..='\u{25FE}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2934}'..='\u{2935}'
+            | '\u{2B05}'..='\u{2B55}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+            | '\u{1F000}'..='\u{1F1E5}'
+            | '\u{1F200}'..='\u{1F3FA}'
+            | '\u{1F400}'..='\u{1FAFF}'
+    )
+}
+
+/// `#`, `*` and digits, which are emoji only as keycaps like `1️⃣`.
+fn is_keycap_base(c: char) -> bool {
+    c.is_ascii_digit() || c == '#' || c == '*'
+}
+
+fn check_zenn_elements(
+    elements: &[Element],
+    mermaid_index: &mut usize,
+    errors: &mut Vec<CheckError>,
+) {
+    for element in elements {
+        match element {
+            Element::Mermaid { code, .. } => {
+                *mermaid_index += 1;
+
+                let chars = code.chars().count();
+                if chars > ZENN_MERMAID_CHARS_MAX {
+                    errors.push(CheckError::MermaidTooLong {
+                        index: *mermaid_index,
+                        chars,
+                    });
+                }
+
+                let chains = code.matches('&').count();
+                if chains > ZENN_MERMAID_CHAINS_MAX {
+                    errors.push(CheckError::MermaidTooManyChains {
+                        index: *mermaid_index,
+                        chains,
+                    });
+                }
+            }
+            Element::Macro(macro_info) => {
+                check_zenn_elements(&macro_info.zenn, mermaid_index, errors)
+            }
+            Element::Message { body, .. } | Element::Details { body, .. } => {
+                check_zenn_elements(body, mermaid_index, errors)
+            }
+            Element::Conditional { platforms, body } if platforms.contains(&Platform::Zenn) => {
+                check_zenn_elements(body, mermaid_index, errors)
+            }
+            element => element
+                .markdown_children()
+                .into_iter()
+                .for_each(|children| check_zenn_elements(children, mermaid_index, errors)),
+        }
+    }
+}
+
+/// Checks the article and its compiled frontmatter against the limits of Qiita.
+pub fn check_qiita(file: &ParsedMd, frontmatter: &QiitaFrontmatter) -> Result<(), Vec<CheckError>> {
+    let mut errors = Vec::new();
+    check_qiita_frontmatter(frontmatter, &mut errors);
+    check_published_at(&file.frontmatter, &mut errors);
+    check_qiita_elements(&file.elements, &mut errors);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+fn check_qiita_frontmatter(frontmatter: &QiitaFrontmatter, errors: &mut Vec<CheckError>) {
+    if !(1..=TOPICS_MAX).contains(&frontmatter.tags.len()) {
+        errors.push(CheckError::InvalidQiitaTagCount(frontmatter.tags.len()));
+    }
+    for tag in &frontmatter.tags {
+        if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',') {
+            errors.push(CheckError::InvalidQiitaTag(tag.clone()));
+        }
+    }
+
+    let title_len = frontmatter.title.trim().chars().count();
+    if title_len == 0 {
+        errors.push(CheckError::EmptyQiitaTitle);
+    } else if title_len > QIITA_TITLE_MAX {
+        errors.push(CheckError::QiitaTitleTooLong(title_len));
+    }
+
+    if let Some(id) = frontmatter.id.as_ref().filter(|id| !id.is_empty()) {
+        if id.len() != QIITA_ID_LEN || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            errors.push(CheckError::InvalidQiitaId(id.clone()));
+        }
+    }
+
+    if let Some(name) = &frontmatter.organization_url_name {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            errors.push(CheckError::InvalidOrganizationName(name.clone()));
+        }
+    }
+}
+
+fn check_qiita_elements(elements: &[Element], errors: &mut Vec<CheckError>) {
+    for element in elements {
+        match element {
+            Element::ArticleLink {
+                text,
+                slug,
+                qiita_id,
+            } => {
+                if qiita_id.is_none() {
+                    errors.push(CheckError::ArticleWithoutQiitaId(slug.clone()));
+                }
+                check_qiita_elements(text, errors);
+            }
+            Element::Macro(macro_info) => check_qiita_elements(&macro_info.qiita, errors),
+            Element::Message { body, .. }
+            | Element::Details { body, .. }
+            | Element::FootnoteDefinition { body, .. }
+            | Element::InlineFootnote(body) => check_qiita_elements(body, errors),
+            Element::Conditional { platforms, body } if platforms.contains(&Platform::Qiita) => {
+                check_qiita_elements(body, errors)
+            }
+            element => element
+                .markdown_children()
+                .into_iter()
+                .for_each(|children| check_qiita_elements(children, errors)),
+        }
+    }
+}
+
+/// Returns warnings for topics missing from `[tags]` that look like a misspelling of a mapped one.
+pub fn check_tags(frontmatter: &ZetaFrontmatter, settings: &Settings) -> Vec<String> {
+    frontmatter
+        .topics
+        .iter()
+        .filter(|topic| !settings.tags.contains_key(*topic))
+        .filter_map(|topic| {
+            settings
+                .tags
+                .keys()
+                .map(|mapped| (mapped, edit_distance(topic, mapped)))
+                .filter(|(_, distance)| {
+                    *distance <= TOPIC_TYPO_DISTANCE_MAX && distance * 2 < topic.chars().count()
+                })
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(mapped, _)| {
+                    format!(
+                        "Topic '{}' is not in [tags] of Zeta.toml. Did you mean '{}'?",
+                        topic, mapped
+                    )
+                })
+        })
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::MessageType;
+
+    fn mermaid(code: String) -> Element {
+        Element::Mermaid {
+            source: format!("```mermaid\n{}```", code),
+            code,
+        }
+    }
+
+    fn chapter(elements: Vec<Element>) -> ParsedMd {
+        ParsedMd::new(ZetaFrontmatter::default(), elements)
+    }
+
+    #[test]
+    fn mermaid_limits() {
+        let long = "a".repeat(ZENN_MERMAID_CHARS_MAX + 1);
+        let chains = "A & ".repeat(ZENN_MERMAID_CHAINS_MAX + 1);
+        assert_eq!(
+            check_zenn_chapter(&chapter(vec![mermaid("a".repeat(ZENN_MERMAID_CHARS_MAX))])),
+            Ok(())
+        );
+        assert_eq!(
+            check_zenn_chapter(&chapter(vec![
+                mermaid("graph TD".to_string()),
+                mermaid(long),
+                mermaid(chains),
+            ])),
+            Err(vec![
+                CheckError::MermaidTooLong {
+                    index: 2,
+                    chars: ZENN_MERMAID_CHARS_MAX + 1
+                },
+                CheckError::MermaidTooManyChains {
+                    index: 3,
+                    chains: ZENN_MERMAID_CHAINS_MAX + 1
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn mermaid_of_other_platforms_is_not_checked() {
+        let long = mermaid("a".repeat(ZENN_MERMAID_CHARS_MAX + 1));
+        let qiita_only = Element::Conditional {
+            platforms: vec![Platform::Qiita],
+            body: vec![long.clone()],
+        };
+        assert_eq!(check_zenn_chapter(&chapter(vec![qiita_only])), Ok(()));
+        let in_message = Element::Message {
+            level: 0,
+            msg_type: MessageType::Info,
+            body: vec![long],
+        };
+        assert!(check_zenn_chapter(&chapter(vec![in_message])).is_err());
+    }
+
+    #[test]
+    fn publication_names() {
+        let mut errors = Vec::new();
+        for name in ["my_team-1", "", "My Team"] {
+            let settings = Settings {
+                publication_name: Some(name.to_string()),
+                ..Settings::default()
+            };
+            check_publication(&ZetaFrontmatter::default(), &settings, &mut errors);
+        }
+        assert_eq!(
+            errors,
+            vec![CheckError::InvalidPublicationName("My Team".to_string())]
+        );
+    }
+
+    #[test]
+    fn topics_close_to_mapped_tags() {
+        let settings: Settings = toml::from_str(
+            "repository = \"\"\n[tags]\njavascript = { qiita = \"JavaScript\" }\nrust = {}\n",
+        )
+        .unwrap();
+        let frontmatter = |topics: &[&str]| ZetaFrontmatter {
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            ..ZetaFrontmatter::default()
+        };
+
+        assert!(check_tags(&frontmatter(&["javascript", "rust"]), &settings).is_empty());
+        assert_eq!(
+            check_tags(&frontmatter(&["javascrpt"]), &settings),
+            ["Topic 'javascrpt' is not in [tags] of Zeta.toml. Did you mean 'javascript'?"]
+        );
+        assert!(check_tags(&frontmatter(&["python", "go", "ruby"]), &settings).is_empty());
+    }
+
+    #[test]
+    fn book_configs() {
+        let config = |price: usize, topics: &[&str], chapters: &[&str]| BookConfig {
+            price,
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            chapters: chapters.iter().map(|chapter| chapter.to_string()).collect(),
+            ..BookConfig::default()
+        };
+        let files = ["intro".to_string(), "1-basics_2".to_string()];
+
+        for price in [0, 200, 1500, 5000] {
+            let config = config(price, &["rust"], &["intro", "1-basics_2"]);
+            assert_eq!(check_zenn_book(&config, &files), Ok(()), "{}", price);
+        }
+        for price in [100, 250, 5100] {
+            assert_eq!(
+                check_zenn_book(&config(price, &[], &[]), &files),
+                Err(vec![CheckError::InvalidBookPrice(price)])
+            );
+        }
+
+        let topics = ["a", "b", "c", "d", "e", "Rust"];
+        assert_eq!(
+            check_zenn_book(&config(0, &topics, &[]), &files),
+            Err(vec![
+                CheckError::TooManyTopics(topics.map(String::from).to_vec()),
+                CheckError::InvalidZennTopic("Rust".to_string()),
+            ])
+        );
+
+        let long = "a".repeat(ZENN_CHAPTER_SLUG_MAX + 1);
+        assert_eq!(
+            check_zenn_book(
+                &config(0, &[], &["intro", "Intro", "", &long, "outro"]),
+                &files
+            ),
+            Err(vec![
+                CheckError::InvalidChapterSlug("Intro".to_string()),
+                CheckError::InvalidChapterSlug(String::new()),
+                CheckError::InvalidChapterSlug(long),
+                CheckError::MissingChapter("outro".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn single_emoji() {
+        for emoji in ["🐟", "☕", "⭐", "✨", "❤️", "👍🏽", "👨‍👩‍👧", "🇯🇵", "1️⃣", "🏳️‍🌈"] {
+            assert!(is_single_emoji(emoji), "{}", emoji);
+        }
+    }
+
+    #[test]
+    fn not_single_emoji() {
+        for emoji in ["", "a", "1", "あ", "漢", "é", "→", "🐟🐟", "🇯🇵🇺🇸", "🐟 ", "1⃣x"] {
+            assert!(!is_single_emoji(emoji), "{}", emoji);
+        }
+    }
+}
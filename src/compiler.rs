@@ -1,5 +1,6 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
+    fmt::Display,
     fs,
     process::{Command, Stdio},
 };
@@ -7,56 +8,253 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ast::{Element, MessageType, ParsedMd, ZetaFrontmatter},
+    article::resolve_qiita_id,
+    ast::{ArticleType, Element, MessageType, ParsedMd, Variable, ZetaFrontmatter},
     print::zeta_error,
-    Settings,
+    r#macro::Platform,
+    schedule, toc, Settings,
 };
 
 #[allow(non_snake_case)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QiitaFrontmatter {
-    title: String,
-    tags: Vec<String>,
-    private: bool,
-    updated_at: String,
-    id: Option<String>,
-    organization_url_name: Option<String>,
-    slide: bool,
-    ignorePublish: bool,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub private: bool,
+    pub updated_at: String,
+    pub id: Option<String>,
+    pub organization_url_name: Option<String>,
+    pub slide: bool,
+    pub ignorePublish: bool,
+}
+
+/// An article that can't be compiled, so nothing is written for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    ZennUserNotSet,
+    QiitaUserNotSet,
+    /// slug of the article without a Qiita id
+    MissingQiitaId(String),
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::ZennUserNotSet => write!(f, "zenn_user is not set in Zeta.toml"),
+            CompileError::QiitaUserNotSet => write!(f, "qiita_user is not set in Zeta.toml"),
+            CompileError::MissingQiitaId(slug) => {
+                write!(f, "The article '{}' has no Qiita id yet", slug)
+            }
+        }
+    }
+}
+
+fn zenn_path(settings: &Settings, slug: &str) -> Result<String, CompileError> {
+    let Some(user) = &settings.zenn_user else {
+        return Err(CompileError::ZennUserNotSet);
+    };
+    Ok(format!("/{}/articles/{}", user, slug))
+}
+
+fn zenn_url(settings: &Settings, slug: &str) -> Result<String, CompileError> {
+    zenn_path(settings, slug).map(|path| format!("https://zenn.dev{}", path))
+}
+
+fn qiita_url(settings: &Settings, slug: &str, id: Option<String>) -> Result<String, CompileError> {
+    let Some(user) = &settings.qiita_user else {
+        return Err(CompileError::QiitaUserNotSet);
+    };
+    let Some(id) = id else {
+        return Err(CompileError::MissingQiitaId(slug.to_string()));
+    };
+    Ok(format!("https://qiita.com/{}/items/{}", user, id))
+}
+
+fn expand_variable(
+    variable: Variable,
+    frontmatter: &ZetaFrontmatter,
+    settings: &Settings,
+    slug: &str,
+) -> Result<String, CompileError> {
+    match variable {
+        Variable::Title => Ok(frontmatter.title.clone()),
+        Variable::Emoji => Ok(frontmatter.emoji.clone()),
+        Variable::Topics => Ok(frontmatter.topics.join(", ")),
+        Variable::Repository => Ok(settings.repository.clone()),
+        Variable::ZennUrl => zenn_url(settings, slug),
+        Variable::QiitaUrl => qiita_url(settings, slug, resolve_qiita_id(slug, frontmatter)),
+    }
+}
+
+/// Text of a heading as shown in the table of contents.
+fn plain_text(
+    elements: &[Element],
+    platform: &Platform,
+    frontmatter: &ZetaFrontmatter,
+    settings: &Settings,
+    slug: &str,
+) -> String {
+    elements
+        .iter()
+        .map(|element| match element {
+            Element::Text(text) | Element::Url(text) => text.clone(),
+            // an error is reported when the heading itself is compiled
+            Element::Variable(variable) => {
+                expand_variable(*variable, frontmatter, settings, slug).unwrap_or_default()
+            }
+            Element::ArticleLink { text, .. } => plain_text(text, platform, frontmatter, settings, slug),
+            Element::Conditional { platforms, body } if platforms.contains(platform) => {
+                plain_text(body, platform, frontmatter, settings, slug)
+            }
+            Element::Macro(macro_info) => {
+                let body = match platform {
+                    Platform::Zenn => &macro_info.zenn,
+                    Platform::Qiita => &macro_info.qiita,
+                };
+                plain_text(body, platform, frontmatter, settings, slug)
+            }
+            Element::InlineCode { code, .. } => code.clone(),
+            element => element
+                .markdown_children()
+                .into_iter()
+                .map(|children| plain_text(children, platform, frontmatter, settings, slug))
+                .collect(),
+        })
+        .collect()
 }
 
 pub struct QiitaCompiler {
+    slug: String,
+    settings: Settings,
+    frontmatter: ZetaFrontmatter,
     existing_fm: Option<QiitaFrontmatter>,
-    footnotes: HashSet<String>,
-    inline_footnotes: HashMap<String, String>,
+    /// whether the article is a slide deck, resolved with the frontmatter
+    slide: bool,
+    /// footnote names in the order of their first reference
+    footnotes: Vec<String>,
+    /// compiled bodies of named and inline footnotes
+    footnote_definitions: HashMap<String, String>,
+    inline_footnotes: usize,
+    /// `(level, text)` of the headings for the table of contents
+    headings: Vec<(usize, String)>,
+    errors: Vec<CompileError>,
 }
 
 impl QiitaCompiler {
-    pub fn new(existing_header: Option<QiitaFrontmatter>) -> Self {
+    pub fn new(slug: &str, settings: Settings, existing_header: Option<QiitaFrontmatter>) -> Self {
         Self {
+            slug: slug.to_string(),
+            settings,
+            frontmatter: ZetaFrontmatter::default(),
             existing_fm: existing_header,
-            footnotes: HashSet::new(),
-            inline_footnotes: HashMap::new(),
+            slide: false,
+            footnotes: Vec::new(),
+            footnote_definitions: HashMap::new(),
+            inline_footnotes: 0,
+            headings: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn compile(mut self, file: ParsedMd) -> String {
-        let mut result = self.compile_frontmatter(file.frontmatter) + &self.compile_elements(file.elements);
-        for (name, content) in &self.inline_footnotes {
-            result.push_str(&format!("\n[^{}]: {}\n", name, content));
+    pub fn compile(mut self, file: ParsedMd) -> Result<String, Vec<CompileError>> {
+        let frontmatter = file.frontmatter.for_qiita();
+        self.frontmatter = frontmatter.clone();
+        self.headings = self.headings(&file.elements);
+        let mut elements = file.elements;
+        if frontmatter.toc && !elements.iter().any(|element| matches!(element, Element::Toc)) {
+            elements.splice(0..0, [Element::Toc, Element::Text("\n".to_string())]);
+        }
+        let mut result = self.compile_frontmatter(frontmatter) + &self.compile_elements(elements);
+
+        let mut unreferenced: Vec<&String> = self
+            .footnote_definitions
+            .keys()
+            .filter(|name| !self.footnotes.contains(name))
+            .collect();
+        unreferenced.sort();
+
+        // named and inline footnotes are numbered together in the order of their first reference
+        for (number, name) in self.footnotes.iter().chain(unreferenced).enumerate() {
+            if let Some(content) = self.footnote_definitions.get(name) {
+                result.push_str(&format!("\n[^{}]: {}\n", number + 1, content));
+            }
         }
 
-        result
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        Ok(result)
     }
 
-    fn compile_frontmatter(&mut self, frontmatter: ZetaFrontmatter) -> String {
-        let mut result = b"---\n".to_vec();
+    fn headings(&self, elements: &[Element]) -> Vec<(usize, String)> {
+        toc::collect_headings(elements, &Platform::Qiita)
+            .into_iter()
+            .map(|(level, text)| {
+                let text = plain_text(&text, &Platform::Qiita, &self.frontmatter, &self.settings, &self.slug);
+                (level, text)
+            })
+            .collect()
+    }
+
+    /// Records why a value can't be compiled. The compile fails after the whole article is checked.
+    fn report(&mut self, value: Result<String, CompileError>) -> String {
+        value.unwrap_or_else(|error| {
+            if !self.errors.contains(&error) {
+                self.errors.push(error);
+            }
+            String::new()
+        })
+    }
+
+    fn reference_footnote(&mut self, name: String) -> String {
+        let number = match self.footnotes.iter().position(|footnote| *footnote == name) {
+            Some(index) => index + 1,
+            None => {
+                self.footnotes.push(name);
+                self.footnotes.len()
+            }
+        };
+        format!("[^{}]", number)
+    }
 
-        let frontmatter = if let Some(existing_fm) = &self.existing_fm {
+    /// Builds the Qiita frontmatter, keeping the fields managed by Qiita CLI in `public/`.
+    pub fn qiita_frontmatter(&self, frontmatter: ZetaFrontmatter) -> QiitaFrontmatter {
+        let private = frontmatter.qiita.as_ref().and_then(|overrides| overrides.private);
+        let slide = frontmatter
+            .qiita
+            .as_ref()
+            .and_then(|overrides| overrides.slide)
+            .or(self.settings.slide);
+        let organization_url_name = frontmatter
+            .qiita
+            .as_ref()
+            .and_then(|overrides| overrides.organization_url_name.clone());
+        let tags = if frontmatter.qiita.as_ref().is_some_and(|overrides| overrides.tags.is_some()) {
+            frontmatter.topics
+        } else {
+            frontmatter
+                .topics
+                .into_iter()
+                .map(|topic| {
+                    self.settings
+                        .tags
+                        .get(&topic)
+                        .and_then(|mapping| mapping.qiita.clone())
+                        .unwrap_or(topic)
+                })
+                .collect()
+        };
+        // Qiita has no reserved publishing, so keep the article unpublished until the time has passed.
+        let ignore_publish = !frontmatter.published
+            || frontmatter
+                .published_at
+                .as_ref()
+                .is_some_and(|published_at| !schedule::is_due(published_at));
+        if let Some(existing_fm) = &self.existing_fm {
             QiitaFrontmatter {
                 title: frontmatter.title,
-                tags: frontmatter.topics,
-                private: existing_fm.private,
+                tags,
+                private: private.unwrap_or(existing_fm.private),
                 updated_at: existing_fm.updated_at.clone(),
                 id: if existing_fm.id.is_some() && !existing_fm.id.as_ref().unwrap().is_empty() {
                         existing_fm.id.clone()
@@ -65,22 +263,33 @@ impl QiitaCompiler {
                     } else {
                         Some("".to_string())
                     },
-                organization_url_name: existing_fm.organization_url_name.clone(),
-                slide: existing_fm.slide,
-                ignorePublish: !frontmatter.published,
+                organization_url_name: organization_url_name
+                    .or_else(|| existing_fm.organization_url_name.clone())
+                    .filter(|name| !name.is_empty()),
+                slide: slide.unwrap_or(existing_fm.slide),
+                ignorePublish: ignore_publish,
             }
         } else {
             QiitaFrontmatter {
                 title: frontmatter.title,
-                tags: frontmatter.topics,
-                private: false,
+                tags,
+                private: private.unwrap_or(false),
                 updated_at: "".to_string(),
                 id: if frontmatter.qiita_id.is_some() { frontmatter.qiita_id } else { Some("".to_string()) },
-                organization_url_name: None,
-                slide: false,
-                ignorePublish: !frontmatter.published,
+                organization_url_name: organization_url_name
+                    .or_else(|| self.settings.organization_url_name.clone())
+                    .filter(|name| !name.is_empty()),
+                slide: slide.unwrap_or(false),
+                ignorePublish: ignore_publish,
             }
-        };
+        }
+    }
+
+    fn compile_frontmatter(&mut self, frontmatter: ZetaFrontmatter) -> String {
+        let mut result = b"---\n".to_vec();
+
+        let frontmatter = self.qiita_frontmatter(frontmatter);
+        self.slide = frontmatter.slide;
         let mut ser = serde_yaml::Serializer::new(&mut result);
         frontmatter.serialize(&mut ser).unwrap();
 
@@ -112,9 +321,20 @@ impl QiitaCompiler {
 
     fn compile_element(&mut self, element: Element) -> String {
         match element {
-            Element::Text(text) => text,
-            Element::Url(url) => format!("\n{}\n", url),
+            Element::Url(url) => url,
+            // a URL within a paragraph gets lines of its own to show as a link card
+            Element::Paragraph(children) => children
+                .into_iter()
+                .map(|child| match child {
+                    Element::Url(url) => format!("\n{}\n", url),
+                    child => self.compile_element(child),
+                })
+                .collect(),
             Element::Macro(macro_info) => self.compile_elements(macro_info.qiita),
+            Element::Variable(variable) => {
+                let value = expand_variable(variable, &self.frontmatter, &self.settings, &self.slug);
+                self.report(value)
+            }
             Element::LinkCard { card_type: _ , url } => {
                 let url = if url.starts_with("/images") {
                     image_path_github(url.as_str())
@@ -123,32 +343,56 @@ impl QiitaCompiler {
                 };
                 format!("\n{}\n", url)
             }
-            Element::Image { alt, url } => {
+            Element::Image {
+                alt,
+                url,
+                width,
+                caption,
+            } => {
                 let url = if url.starts_with("/images") {
                     image_path_github(url.as_str())
                 } else {
                     url
                 };
-                format!("![{}]({})", alt, url)
-            }
-            Element::InlineFootnote(content) => {
-                let mut i: usize = 1;
-                let name = loop {
-                    let name = format!("zeta.inline.{}", i);
-                    if !self.inline_footnotes.contains_key(&name) {
-                        break name;
+                let image = match width {
+                    Some(width) => {
+                        format!(
+                            "<img src=\"{}\" alt=\"{}\" width=\"{}\">",
+                            escape_attribute(&url),
+                            escape_attribute(&alt),
+                            width
+                        )
                     }
-                    i += 1;
+                    None => format!("![{}]({})", alt, url),
                 };
-
-                self.inline_footnotes.insert(name.clone(), content);
-
-                format!("[^{}]", name)
+                match caption {
+                    Some(caption) => format!("{}\n*{}*", image, caption),
+                    None => image,
+                }
             }
-            Element::Footnote(name) => {
-                let result = format!("[^{}]", &name);
-                self.footnotes.insert(name);
-                result
+            Element::ArticleLink {
+                text,
+                slug,
+                qiita_id,
+            } => {
+                let text = self.compile_elements(text);
+                let url = qiita_url(&self.settings, &slug, qiita_id);
+                let url = self.report(url);
+                format!("[{}]({})", text, url)
+            }
+            Element::InlineFootnote(content) => {
+                self.inline_footnotes += 1;
+                let name = format!("zeta.inline.{}", self.inline_footnotes);
+                let reference = self.reference_footnote(name.clone());
+                let content = self.compile_elements(content);
+                self.footnote_definitions.insert(name, content);
+                reference
+            }
+            Element::Footnote(name) => self.reference_footnote(name),
+            Element::FootnoteDefinition { name, body } => {
+                let body = self.compile_elements(without_markers(body));
+                self.footnote_definitions.insert(name, body);
+                String::new()
             }
             Element::Message {
                 level: _,
@@ -161,8 +405,7 @@ impl QiitaCompiler {
                     MessageType::Alert => "alert",
                 };
 
-                let mut compiler = QiitaCompiler::new(None);
-                let body = compiler.compile_elements(body);
+                let body = self.compile_elements(without_markers(body));
 
                 format!(":::note {}\n{}:::", msg_type, body)
             }
@@ -171,17 +414,78 @@ impl QiitaCompiler {
                 title,
                 body,
             } => {
-                let mut compiler = QiitaCompiler::new(None);
-                let body = compiler.compile_elements(body);
+                let body = self.compile_elements(without_markers(body));
                 format!(
                     "<details><summary>{}</summary>\n{}</details>\n",
                     title, body
                 )
             }
+            Element::Mermaid { source, .. } => source,
+            Element::Heading { level: _, text } => self.compile_elements(text),
+            Element::Toc => toc::render(&self.headings, toc::qiita_anchor),
+            // only slide decks are paged, other articles get a section like on Zenn
+            Element::SlideBreak { title } => match title {
+                Some(title) if self.slide => format!("\n---\n# {}", title),
+                Some(title) => format!("\n## {}", title),
+                None => "\n---".to_string(),
+            },
+            Element::Conditional { platforms, body } => {
+                if platforms.contains(&Platform::Qiita) {
+                    self.compile_elements(body)
+                } else {
+                    String::new()
+                }
+            }
+            element => compile_markdown(element, |children| self.compile_elements(children)),
         }
     }
 }
 
+/// Removes the opening and closing lines kept as trivia from the body of a container, for the
+/// backends that write their own.
+fn without_markers(mut body: Vec<Element>) -> Vec<Element> {
+    if matches!(body.last(), Some(Element::Trivia(_))) {
+        body.pop();
+    }
+    if matches!(body.first(), Some(Element::Trivia(_))) {
+        body.remove(0);
+    }
+    body
+}
+
+/// Compiles the CommonMark nodes, which read the same on both platforms, from their source.
+fn compile_markdown(element: Element, mut compile: impl FnMut(Vec<Element>) -> String) -> String {
+    match element {
+        Element::Text(text) | Element::Trivia(text) => text,
+        Element::Paragraph(children)
+        | Element::BlockQuote(children)
+        | Element::Html(children)
+        | Element::Emphasis(children)
+        | Element::Strong(children)
+        | Element::Strikethrough(children)
+        | Element::Link { text: children, .. } => compile(children),
+        Element::List { items, .. } => items.into_iter().map(compile).collect(),
+        Element::CodeBlock(source) | Element::ThematicBreak(source) => source,
+        Element::Table { header, rows, .. } => header
+            .into_iter()
+            .chain(rows.into_iter().flatten())
+            .map(compile)
+            .collect(),
+        Element::InlineCode { code, fence } => {
+            let fence = "`".repeat(fence);
+            format!("{}{}{}", fence, code, fence)
+        }
+        element => unreachable!("{:?} is compiled by the platform compilers", element),
+    }
+}
+
+/// Escapes text for a double-quoted HTML attribute.
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+
 fn image_path_github(path: &str) -> String {
     let Ok(settings) = fs::read_to_string("./Zeta.toml") else {
         zeta_error("Failed to read Zeta.toml");
@@ -214,7 +518,7 @@ fn image_path_github(path: &str) -> String {
     let mut main_branch = String::from_utf8(grep.wait_with_output().unwrap().stdout)
         .unwrap()
         .split(' ')
-        .last()
+        .next_back()
         .unwrap()
         .to_string();
 
@@ -235,30 +539,100 @@ fn image_path_github(path: &str) -> String {
 pub struct ZennFrontmatter {
     title: String,
     emoji: String,
-    r#type: String,
+    r#type: ArticleType,
     topics: Vec<String>,
     published: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publication_name: Option<String>,
 }
 
-pub struct ZennCompiler;
+/// Resolves the Zenn Publication of an article. An empty name opts out of the project default.
+pub fn publication_name(frontmatter: &ZetaFrontmatter, settings: &Settings) -> Option<String> {
+    frontmatter
+        .zenn
+        .as_ref()
+        .and_then(|overrides| overrides.publication_name.clone())
+        .or_else(|| settings.publication_name.clone())
+        .filter(|name| !name.is_empty())
+}
+
+pub struct ZennCompiler {
+    slug: String,
+    settings: Settings,
+    frontmatter: ZetaFrontmatter,
+    /// `(level, text)` of the headings for the table of contents
+    headings: Vec<(usize, String)>,
+    errors: Vec<CompileError>,
+}
 
 impl ZennCompiler {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(slug: &str, settings: Settings) -> Self {
+        Self {
+            slug: slug.to_string(),
+            settings,
+            frontmatter: ZetaFrontmatter::default(),
+            headings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn compile(mut self, file: ParsedMd) -> Result<String, Vec<CompileError>> {
+        let frontmatter = file.frontmatter.for_zenn();
+        self.frontmatter = frontmatter.clone();
+        self.headings = self.headings(&file.elements);
+        let result = self.compile_header(frontmatter) + &self.compile_elements(file.elements);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        Ok(result)
     }
 
-    pub fn compile(mut self, file: ParsedMd) -> String {
-        self.compile_header(file.frontmatter) + &self.compile_elements(file.elements)
+    /// Compiles only the body, for book chapters whose header is written by the caller.
+    pub fn compile_body(mut self, file: ParsedMd) -> Result<String, Vec<CompileError>> {
+        self.frontmatter = file.frontmatter.for_zenn();
+        self.headings = self.headings(&file.elements);
+        let result = self.compile_elements(file.elements);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        Ok(result)
+    }
+
+    fn headings(&self, elements: &[Element]) -> Vec<(usize, String)> {
+        toc::collect_headings(elements, &Platform::Zenn)
+            .into_iter()
+            .map(|(level, text)| {
+                let text = plain_text(&text, &Platform::Zenn, &self.frontmatter, &self.settings, &self.slug);
+                (level, text)
+            })
+            .collect()
+    }
+
+    /// Records why a value can't be compiled. The compile fails after the whole article is checked.
+    fn report(&mut self, value: Result<String, CompileError>) -> String {
+        value.unwrap_or_else(|error| {
+            if !self.errors.contains(&error) {
+                self.errors.push(error);
+            }
+            String::new()
+        })
     }
 
     fn compile_header(&mut self, frontmatter: ZetaFrontmatter) -> String {
         let mut result = b"---\n".to_vec();
+        let publication_name = publication_name(&frontmatter, &self.settings);
         let frontmatter = ZennFrontmatter {
             title: frontmatter.title,
             emoji: frontmatter.emoji,
             r#type: frontmatter.r#type,
             topics: frontmatter.topics,
             published: frontmatter.published,
+            published_at: frontmatter.published_at,
+            publication_name,
         };
         let mut ser = serde_yaml::Serializer::new(&mut result);
         frontmatter.serialize(&mut ser).unwrap();
@@ -275,48 +649,358 @@ impl ZennCompiler {
 
     fn compile_element(&mut self, element: Element) -> String {
         match element {
-            Element::Text(text) => text,
-            Element::Url(url) => format!("{}", url),
+            Element::Url(url) => url,
             Element::Macro(macro_info) => self.compile_elements(macro_info.zenn),
+            Element::Variable(variable) => {
+                let value = expand_variable(variable, &self.frontmatter, &self.settings, &self.slug);
+                self.report(value)
+            }
             Element::LinkCard { card_type, url } => {
                 format!("@[{}]({})", card_type, url)
             }
-            Element::Image { alt, url } => {
-                format!("![{}]({})", alt, url)
+            Element::Image {
+                alt,
+                url,
+                width,
+                caption,
+            } => {
+                let image = match width {
+                    Some(width) => format!("![{}]({} ={}x)", alt, url, width),
+                    None => format!("![{}]({})", alt, url),
+                };
+                match caption {
+                    Some(caption) => format!("{}\n*{}*", image, caption),
+                    None => image,
+                }
+            }
+            Element::ArticleLink { text, slug, .. } => {
+                let text = self.compile_elements(text);
+                let path = zenn_path(&self.settings, &slug);
+                let path = self.report(path);
+                format!("[{}]({})", text, path)
+            }
+            Element::InlineFootnote(content) => {
+                format!("^[{}]", self.compile_elements(content))
             }
-            Element::InlineFootnote(content) => format!("^[{}]", content),
             Element::Footnote(name) => format!("[^{}]", name),
-            Element::Message {
-                level,
-                msg_type,
+            Element::FootnoteDefinition { body, .. }
+            | Element::Message {
+                msg_type: MessageType::Alert,
                 body,
-            } => {
-                let msg_type = match msg_type {
-                    MessageType::Info => "",
-                    MessageType::Warn => "",
-                    MessageType::Alert => "alert",
-                };
-
-                let mut compiler = ZennCompiler {};
-                let body = compiler.compile_elements(body);
-
-                format!(
-                    ":::{0}message {1}{2}:::{0}",
-                    ":".repeat(level),
-                    msg_type,
-                    body
-                )
+                ..
             }
-            Element::Details { level, title, body } => {
-                let mut compiler = ZennCompiler {};
-                let body = compiler.compile_elements(body);
-                format!(
-                    ":::{0}details {1}{2}:::{0}",
-                    ":".repeat(level),
-                    title,
-                    body
-                )
+            | Element::Details { body, .. } => self.compile_elements(body),
+            // Zenn has no info or warn type, so they become plain messages
+            Element::Message { level, body, .. } => {
+                let body = self.compile_elements(without_markers(body));
+                format!(":::{0}message {1}:::{0}", ":".repeat(level), body)
             }
+            Element::Mermaid { source, .. } => source,
+            Element::Heading { level: _, text } => self.compile_elements(text),
+            Element::Toc => toc::render(&self.headings, toc::zenn_anchor),
+            Element::SlideBreak { title } => match title {
+                Some(title) => format!("\n## {}", title),
+                None => "\n---".to_string(),
+            },
+            Element::Conditional { platforms, body } => {
+                if platforms.contains(&Platform::Zenn) {
+                    self.compile_elements(body)
+                } else {
+                    String::new()
+                }
+            }
+            element => compile_markdown(element, |children| self.compile_elements(children)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{MarkdownDoc, QiitaOverrides, ZennOverrides},
+        parser::Parser,
+        scanner::Scanner,
+    };
+
+    fn parse(source: &str) -> ParsedMd {
+        let tokens = Scanner::new(source.chars().collect())
+            .scan_snippet()
+            .unwrap();
+        Parser::new(MarkdownDoc::new(String::new(), tokens))
+            .parse_with(ZetaFrontmatter::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn zenn_reproduces_what_it_does_not_convert() {
+        let source = "## Title  \n\nsome *text* and [^a] and ^[inline]\n\nhttps://example.com\n\n\
+            @[card](https://example.com)\n\n![alt](https://example.com/a.png =250x)\n*caption*\n\n\
+            ```mermaid\ngraph TD\n  A --> B\n```\n\n:::details  title\nbody\n:::\n\n\
+            :::message alert\nwarning\n:::\n\n- a\n  - b\n\n[^a]:   note  \nend\n";
+        let zenn = ZennCompiler::new("slug", Settings::default())
+            .compile_body(parse(source))
+            .unwrap();
+        assert_eq!(zenn, source);
+    }
+
+    #[test]
+    fn qiita_reproduces_what_it_does_not_convert() {
+        let source = "## Title  \n\nsome *text*  \nand more\n\nhttps://example.com\n\n\
+            ```mermaid\ngraph TD\n  A --> B\n```\n\n> quote\n\n| a | b |\n|-|-|\n| 1 | 2 |\n\n\
+            - a\n  - b\n";
+        let qiita = QiitaCompiler::new("slug", Settings::default(), None)
+            .compile(parse(source))
+            .unwrap();
+        let (_, body) = qiita.split_once("\n---\n").unwrap();
+        assert_eq!(body, source);
+    }
+
+    #[test]
+    fn image_width() {
+        let source = "![a \"b\" <c> & d](https://example.com/a.png?x=1&y=2 =250x)\n*caption*\n";
+        let zenn = ZennCompiler::new("slug", Settings::default())
+            .compile_body(parse(source))
+            .unwrap();
+        assert_eq!(zenn, source);
+
+        let qiita = QiitaCompiler::new("slug", Settings::default(), None)
+            .compile(parse(source))
+            .unwrap();
+        let (_, body) = qiita.split_once("\n---\n").unwrap();
+        assert_eq!(
+            body,
+            "<img src=\"https://example.com/a.png?x=1&amp;y=2\" \
+             alt=\"a &quot;b&quot; &lt;c> &amp; d\" width=\"250\">\n*caption*\n"
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        let source = "a<only zenn> z</only><only qiita> q</only>\n\n\
+            :::only qiita\nqiita <only zenn>never</only>\n:::\n\n\
+            <only zenn, qiita>both <only zenn>zenn</only></only>\n";
+        let zenn = ZennCompiler::new("slug", Settings::default())
+            .compile_body(parse(source))
+            .unwrap();
+        assert_eq!(zenn, "a z\n\n\n\nboth zenn\n");
+
+        let qiita = QiitaCompiler::new("slug", Settings::default(), None)
+            .compile(parse(source))
+            .unwrap();
+        let (_, body) = qiita.split_once("\n---\n").unwrap();
+        assert_eq!(body, "a q\n\n\nqiita \n\n\nboth \n");
+    }
+
+    #[test]
+    fn article_links() {
+        let link = |qiita_id: Option<&str>| ParsedMd {
+            elements: vec![Element::ArticleLink {
+                text: vec![Element::Text("other".to_string())],
+                slug: "other".to_string(),
+                qiita_id: qiita_id.map(str::to_string),
+            }],
+            frontmatter: ZetaFrontmatter::default(),
+        };
+        let settings = Settings {
+            zenn_user: Some("zenn".to_string()),
+            qiita_user: Some("qiita".to_string()),
+            ..Settings::default()
+        };
+
+        let zenn = ZennCompiler::new("slug", settings.clone())
+            .compile_body(link(None))
+            .unwrap();
+        assert_eq!(zenn, "[other](/zenn/articles/other)");
+        let errors = ZennCompiler::new("slug", Settings::default())
+            .compile_body(link(None))
+            .unwrap_err();
+        assert_eq!(errors, vec![CompileError::ZennUserNotSet]);
+
+        let qiita = QiitaCompiler::new("slug", settings.clone(), None)
+            .compile(link(Some("0123456789abcdef0123")))
+            .unwrap();
+        let (_, body) = qiita.split_once("\n---\n").unwrap();
+        assert_eq!(
+            body,
+            "[other](https://qiita.com/qiita/items/0123456789abcdef0123)"
+        );
+        let errors = QiitaCompiler::new("slug", settings, None)
+            .compile(link(None))
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![CompileError::MissingQiitaId("other".to_string())]
+        );
+    }
+
+    #[test]
+    fn qiita_overrides_take_precedence() {
+        let settings: Settings = toml::from_str(
+            "repository = \"\"\norganization_url_name = \"org\"\n[tags]\nrust = { qiita = \"Rust\" }\n",
+        )
+        .unwrap();
+        let shared = "title: Shared\nemoji: 📝\ntype: tech\ntopics: [rust, cli]\npublished: true\n";
+        let compiler = QiitaCompiler::new("slug", settings, None);
+
+        let frontmatter: ZetaFrontmatter = serde_yaml::from_str(shared).unwrap();
+        let qiita = compiler.qiita_frontmatter(frontmatter.for_qiita());
+        assert_eq!(qiita.title, "Shared");
+        assert_eq!(qiita.tags, ["Rust", "cli"]);
+        assert!(!qiita.private);
+        assert_eq!(qiita.organization_url_name.as_deref(), Some("org"));
+
+        let frontmatter: ZetaFrontmatter = serde_yaml::from_str(&format!(
+            "{}qiita:\n  title: Qiita\n  tags: [rust]\n  private: true\n  organization_url_name: other\n",
+            shared
+        ))
+        .unwrap();
+        let qiita = compiler.qiita_frontmatter(frontmatter.for_qiita());
+        assert_eq!(qiita.title, "Qiita");
+        assert_eq!(qiita.tags, ["rust"]);
+        assert!(qiita.private);
+        assert_eq!(qiita.organization_url_name.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn publications() {
+        let settings = Settings {
+            publication_name: Some("team".to_string()),
+            ..Settings::default()
+        };
+        let frontmatter = |publication_name: Option<&str>| ZetaFrontmatter {
+            zenn: Some(ZennOverrides {
+                publication_name: publication_name.map(str::to_string),
+                ..ZennOverrides::default()
+            }),
+            ..ZetaFrontmatter::default()
+        };
+
+        assert_eq!(
+            publication_name(&ZetaFrontmatter::default(), &Settings::default()),
+            None
+        );
+        assert_eq!(
+            publication_name(&frontmatter(None), &settings).as_deref(),
+            Some("team")
+        );
+        assert_eq!(
+            publication_name(&frontmatter(Some("other")), &settings).as_deref(),
+            Some("other")
+        );
+        assert_eq!(publication_name(&frontmatter(Some("")), &settings), None);
+    }
+
+    #[test]
+    fn slide_and_organization_defaults() {
+        let existing = QiitaFrontmatter {
+            title: String::new(),
+            tags: Vec::new(),
+            private: false,
+            updated_at: String::new(),
+            id: Some("0123456789abcdef0123".to_string()),
+            organization_url_name: Some("existing".to_string()),
+            slide: true,
+            ignorePublish: false,
+        };
+        let settings = Settings {
+            organization_url_name: Some("org".to_string()),
+            slide: Some(true),
+            ..Settings::default()
+        };
+        let slide_off = ZetaFrontmatter {
+            qiita: Some(QiitaOverrides {
+                slide: Some(false),
+                ..QiitaOverrides::default()
+            }),
+            ..ZetaFrontmatter::default()
+        };
+
+        let new = QiitaCompiler::new("slug", Settings::default(), None)
+            .qiita_frontmatter(ZetaFrontmatter::default());
+        assert!(!new.slide);
+        assert_eq!(new.organization_url_name, None);
+
+        let compiler = QiitaCompiler::new("slug", settings.clone(), None);
+        let new = compiler.qiita_frontmatter(ZetaFrontmatter::default());
+        assert!(new.slide);
+        assert_eq!(new.organization_url_name.as_deref(), Some("org"));
+        assert!(!compiler.qiita_frontmatter(slide_off).slide);
+
+        // without a setting or an override, the values in public/ are kept
+        let kept = QiitaCompiler::new("slug", Settings::default(), Some(existing))
+            .qiita_frontmatter(ZetaFrontmatter::default());
+        assert!(kept.slide);
+        assert_eq!(kept.organization_url_name.as_deref(), Some("existing"));
+    }
+
+    #[test]
+    fn slide_breaks() {
+        let source = "a\n:::slide Title\nb\n:::slide\nc\n";
+        let slide = |slide: Option<bool>| ZetaFrontmatter {
+            qiita: Some(QiitaOverrides {
+                slide,
+                ..QiitaOverrides::default()
+            }),
+            ..ZetaFrontmatter::default()
+        };
+        let compile = |frontmatter: ZetaFrontmatter| {
+            let mut file = parse(source);
+            file.frontmatter = frontmatter;
+            let qiita = QiitaCompiler::new("slug", Settings::default(), None)
+                .compile(file)
+                .unwrap();
+            qiita.split_once("\n---\n").unwrap().1.to_string()
+        };
+
+        assert_eq!(
+            compile(slide(Some(true))),
+            "a\n\n---\n# Title\nb\n\n---\nc\n"
+        );
+        assert_eq!(compile(slide(Some(false))), "a\n\n## Title\nb\n\n---\nc\n");
+        assert_eq!(compile(slide(None)), compile(slide(Some(false))));
+
+        let zenn = ZennCompiler::new("slug", Settings::default())
+            .compile_body(parse(source))
+            .unwrap();
+        assert_eq!(zenn, "a\n\n## Title\nb\n\n---\nc\n");
+    }
+
+    #[test]
+    fn variables() {
+        let mut file = parse("{{title}} by {{repository}}: {{zenn_url}} {{qiita_url}}\n");
+        file.frontmatter.title = "Title".to_string();
+        file.frontmatter.qiita_id = Some("0123456789abcdef0123".to_string());
+        let settings = Settings {
+            repository: "owner/repo".to_string(),
+            zenn_user: Some("zenn".to_string()),
+            qiita_user: Some("qiita".to_string()),
+            ..Settings::default()
+        };
+        let zenn = ZennCompiler::new("slug", settings.clone())
+            .compile_body(file.clone())
+            .unwrap();
+        assert_eq!(
+            zenn,
+            "Title by owner/repo: https://zenn.dev/zenn/articles/slug \
+             https://qiita.com/qiita/items/0123456789abcdef0123\n"
+        );
+
+        let errors = ZennCompiler::new("slug", Settings::default())
+            .compile_body(file.clone())
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![CompileError::ZennUserNotSet, CompileError::QiitaUserNotSet]
+        );
+
+        file.frontmatter.qiita_id = None;
+        let errors = ZennCompiler::new("slug", settings)
+            .compile_body(file)
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![CompileError::MissingQiitaId("slug".to_string())]
+        );
+    }
+}
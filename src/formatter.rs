@@ -0,0 +1,351 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    ast::ZetaFrontmatter,
+    r#macro::StringMacro,
+    scanner::{Region, Regions, Scanner},
+    token::{Token, TokenType},
+};
+
+const SEPARATOR: &str = "---\n";
+/// colons of a `:::` block without nested blocks
+const BLOCK_COLONS_MIN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    MissingFrontmatter,
+    InvalidFrontmatter(String),
+    UnknownFrontmatterKey(String),
+    FrontmatterComment,
+    InvalidMacro(String),
+    InvalidSyntax(String),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::MissingFrontmatter => write!(f, "The article has no frontmatter."),
+            FormatError::InvalidFrontmatter(error) => write!(f, "Invalid frontmatter: {}", error),
+            FormatError::UnknownFrontmatterKey(key) => write!(
+                f,
+                "Unknown frontmatter key '{}' would be removed by formatting.",
+                key
+            ),
+            FormatError::FrontmatterComment => write!(
+                f,
+                "Comments in the frontmatter would be removed by formatting."
+            ),
+            FormatError::InvalidMacro(error) => write!(f, "Invalid macro: {}", error),
+            FormatError::InvalidSyntax(error) => write!(f, "Invalid syntax: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Formats the source of a zeta article.
+pub fn format(source: &str) -> Result<String, FormatError> {
+    let source = source
+        .trim_start()
+        .strip_prefix(SEPARATOR)
+        .ok_or(FormatError::MissingFrontmatter)?;
+    let end = source
+        .find(SEPARATOR)
+        .ok_or(FormatError::MissingFrontmatter)?;
+    let frontmatter = format_frontmatter(&source[..end])?;
+    let body = format_body(&format_macros(&source[end + SEPARATOR.len()..])?)?;
+    Ok(format!("{0}{1}{0}{2}", SEPARATOR, frontmatter, body))
+}
+
+/// Serializes the frontmatter the way `zeta new` writes it.
+fn format_frontmatter(yaml: &str) -> Result<String, FormatError> {
+    if yaml.lines().any(|line| line.trim_start().starts_with('#')) {
+        return Err(FormatError::FrontmatterComment);
+    }
+    let error = |error: serde_yaml::Error| FormatError::InvalidFrontmatter(error.to_string());
+    let frontmatter: ZetaFrontmatter = serde_yaml::from_str(yaml).map_err(error)?;
+    let formatted = serde_yaml::to_string(&frontmatter).map_err(error)?;
+
+    let original: serde_yaml::Value = serde_yaml::from_str(yaml).map_err(error)?;
+    let result: serde_yaml::Value = serde_yaml::from_str(&formatted).map_err(error)?;
+    if let Some(key) = dropped_key(&original, &result) {
+        return Err(FormatError::UnknownFrontmatterKey(key));
+    }
+    Ok(formatted)
+}
+
+/// A key with a value that is missing after the round trip through `ZetaFrontmatter`.
+/// Keys with empty values are left out on purpose.
+fn dropped_key(original: &serde_yaml::Value, result: &serde_yaml::Value) -> Option<String> {
+    let (serde_yaml::Value::Mapping(original), serde_yaml::Value::Mapping(result)) =
+        (original, result)
+    else {
+        return None;
+    };
+    original.iter().find_map(|(key, value)| {
+        let name = key.as_str().unwrap_or_default();
+        match result.get(key) {
+            Some(formatted) => dropped_key(value, formatted).map(|key| format!("{}.{}", name, key)),
+            None if is_empty(value) => None,
+            None => Some(name.to_string()),
+        }
+    })
+}
+
+fn is_empty(value: &serde_yaml::Value) -> bool {
+    match value {
+        serde_yaml::Value::Null => true,
+        serde_yaml::Value::Bool(value) => !value,
+        serde_yaml::Value::Sequence(values) => values.is_empty(),
+        serde_yaml::Value::Mapping(values) => values.is_empty(),
+        _ => false,
+    }
+}
+
+/// The tokens of a body and its code and macros as byte ranges.
+fn scan(body: &str) -> Result<(Vec<Token>, Regions), FormatError> {
+    let (tokens, regions) = Scanner::new(body.chars().collect())
+        .scan_regions()
+        .map_err(|errors| FormatError::InvalidSyntax(errors[0].error_type.to_string()))?;
+    let offsets: Vec<usize> = body
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([body.len()])
+        .collect();
+    let regions = regions
+        .into_iter()
+        .map(|(range, region)| (offsets[range.start]..offsets[range.end], region))
+        .collect();
+    Ok((tokens, regions))
+}
+
+/// Rewrites the YAML of each `<macro>` as block mappings indented by the serializer.
+fn format_macros(body: &str) -> Result<String, FormatError> {
+    let (_, regions) = scan(body)?;
+    let mut result = String::new();
+    let mut p = 0;
+    for (range, region) in regions {
+        if region != Region::Macro {
+            continue;
+        }
+        let error = |error: serde_yaml::Error| FormatError::InvalidMacro(error.to_string());
+        let r#macro: StringMacro = serde_yaml::from_str(&body[range.clone()]).map_err(error)?;
+        let mut mapping = serde_yaml::Mapping::new();
+        if let Some(zenn) = r#macro.zenn {
+            mapping.insert("zenn".into(), zenn.into());
+        }
+        if let Some(qiita) = r#macro.qiita {
+            mapping.insert("qiita".into(), qiita.into());
+        }
+        result.push_str(&body[p..range.start]);
+        result.push('\n');
+        result.push_str(&serde_yaml::to_string(&mapping).map_err(error)?);
+        p = range.end;
+    }
+    result.push_str(&body[p..]);
+    Ok(result)
+}
+
+/// `:::message`, `:::details` or `:::only`, and the `:::` closing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Begin(usize),
+    End(usize),
+}
+
+/// Collects the `:::` markers by line index, and the lines of the headings.
+fn collect_lines(
+    tokens: &[Token],
+    markers: &mut BTreeMap<usize, Marker>,
+    headings: &mut Vec<usize>,
+) {
+    for token in tokens {
+        let line = token.row - 1;
+        match &token.token_type {
+            TokenType::MessageBegin { level, .. }
+            | TokenType::DetailsBegin { level, .. }
+            | TokenType::ConditionalBegin { level, .. } => {
+                markers.insert(line, Marker::Begin(*level));
+            }
+            TokenType::MessageOrDetailsEnd { level } => {
+                markers.insert(line, Marker::End(*level));
+            }
+            TokenType::Heading { .. } => headings.push(line),
+            TokenType::Conditional { body, .. } => collect_lines(body, markers, headings),
+            _ => (),
+        }
+    }
+}
+
+fn format_body(body: &str) -> Result<String, FormatError> {
+    let (tokens, regions) = scan(body)?;
+    let mut markers = BTreeMap::new();
+    let mut headings = Vec::new();
+    collect_lines(&tokens, &mut markers, &mut headings);
+
+    // lines that start inside code or a macro, or that code runs past, are kept as they are
+    let mut lines: Vec<String> = Vec::new();
+    let mut verbatim = Vec::new();
+    let mut offset = 0;
+    for line in body.split('\n') {
+        let end = offset + line.len();
+        verbatim.push(regions.iter().any(|(range, _)| {
+            range.start < end + 1
+                && offset < range.end
+                && (range.start <= offset || range.end > end)
+        }));
+        offset = end + 1;
+        lines.push(line.to_string());
+    }
+
+    let markers: Vec<(usize, Marker)> = markers.into_iter().collect();
+    let mut blocks = Vec::new();
+    if match_blocks(&markers, 0, None, &mut blocks).is_none() {
+        blocks.clear();
+    }
+    for (begin, end, height) in blocks {
+        for i in [begin, end] {
+            lines[i] = set_colons(&lines[i], BLOCK_COLONS_MIN + height);
+        }
+    }
+
+    let mut result: Vec<String> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if verbatim[i] {
+            result.push(line.clone());
+            continue;
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            if result.last().is_some_and(|line| !line.is_empty()) {
+                result.push(String::new());
+            }
+            continue;
+        }
+        let next_is_text = lines.get(i + 1).is_some_and(|line| {
+            !line.trim().is_empty() && !markers.iter().any(|(marker, _)| *marker == i + 1)
+        });
+        if line.ends_with("  ") && next_is_text && !headings.contains(&i) {
+            // hard line break
+            result.push(format!("{}  ", trimmed));
+        } else {
+            result.push(trimmed.to_string());
+        }
+    }
+    while result.last().is_some_and(String::is_empty) {
+        result.pop();
+    }
+    if result.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(result.join("\n") + "\n")
+}
+
+/// Pairs the markers from `markers[i]` like the parser, up to the `:::` of level `until`,
+/// and collects `(begin line, end line, height)` of the blocks.
+/// Returns the height of the blocks found and the index after the closing marker.
+fn match_blocks(
+    markers: &[(usize, Marker)],
+    mut i: usize,
+    until: Option<usize>,
+    blocks: &mut Vec<(usize, usize, usize)>,
+) -> Option<(usize, usize)> {
+    let mut height = 0;
+    while i < markers.len() {
+        match markers[i] {
+            (begin, Marker::Begin(level)) => {
+                let (inner, next) = match_blocks(markers, i + 1, Some(level), blocks)?;
+                blocks.push((begin, markers[next - 1].0, inner));
+                height = height.max(inner + 1);
+                i = next;
+            }
+            (_, Marker::End(level)) if Some(level) == until => return Some((height, i + 1)),
+            // the parser ignores a `:::` closing nothing
+            (_, Marker::End(_)) => i += 1,
+        }
+    }
+    until.is_none().then_some((height, i))
+}
+
+fn set_colons(line: &str, colons: usize) -> String {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = line[indent..].trim_start_matches(':');
+    format!("{}{}{}", &line[..indent], ":".repeat(colons), rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRONTMATTER: &str =
+        "---\ntitle: t\nemoji: 😀\ntype: tech\ntopics: [rust]\npublished: false\n---\n";
+
+    fn format_body(body: &str) -> String {
+        let formatted = format(&format!("{}{}", FRONTMATTER, body)).unwrap();
+        formatted.split_once("\n---\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn frontmatter() {
+        let source = "---\npublished: false\ntopics: [\"rust\"]\ntype: 'tech'\nemoji: \"😀\"\ntitle: \"t\"\n---\n";
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, format(FRONTMATTER).unwrap());
+        assert!(formatted.starts_with("---\ntitle: t\nemoji: 😀\ntype: tech\n"));
+    }
+
+    #[test]
+    fn colons() {
+        let nested = ":::message\n:::details a\n:::only zenn\nx\n:::\n:::\n:::\n";
+        assert_eq!(
+            format_body(":::message\n::::details a\n:::::only zenn\nx\n:::::\n::::\n:::\n"),
+            ":::::message\n::::details a\n:::only zenn\nx\n:::\n::::\n:::::\n"
+        );
+        // the parser refuses blocks nested with as many colons as the outer one
+        assert_eq!(
+            format_body(nested),
+            ":::::message\n::::details a\n:::only zenn\nx\n:::\n::::\n:::::\n"
+        );
+        let siblings = ":::message\nx\n:::\n\n:::details a\ny\n:::\n";
+        assert_eq!(format_body(siblings), siblings);
+        assert_eq!(
+            format_body("::::message\nx\n::::\n"),
+            ":::message\nx\n:::\n"
+        );
+    }
+
+    #[test]
+    fn macros() {
+        assert_eq!(
+            format_body("<macro>\n{zenn: a,   qiita: b}\n</macro>\n"),
+            "<macro>\nzenn: a\nqiita: b\n</macro>\n"
+        );
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(
+            format_body("a  \nb \n\n\n\nc  \n\n## h  \nd\t\n\n"),
+            "a  \nb\n\nc\n\n## h\nd\n"
+        );
+    }
+
+    #[test]
+    fn code_is_left_as_it_is() {
+        let code = [
+            "```rust\na  \n\n\n:::message\n```\n",
+            "~~~\na  \n\n\n::::message\n~~~\n",
+            "x\n\n    a  \n\n\n    ::::message\n",
+            "`a  \n\n b`\n",
+        ];
+        for code in code {
+            assert_eq!(format_body(code), code);
+        }
+    }
+
+    #[test]
+    fn idempotent() {
+        let body = "\n:::message\n::::details a  \nx  \ny\n\n\n::::\n:::\n<macro>\n{zenn: a}\n</macro>\n\n```\n:::  \n\n\n```\n\n    b  \n";
+        let formatted = format(&format!("{}{}", FRONTMATTER, body)).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+}
@@ -0,0 +1,639 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufRead, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    ast::ZetaFrontmatter,
+    parser,
+    r#macro::StringMacro,
+    scanner::Scanner,
+    token::{Token, TokenType},
+    MACROS_FILE,
+};
+
+/// `:::message` types of zeta
+const MESSAGE_TYPES: [&str; 3] = ["info", "warn", "alert"];
+/// `@[type](url)` embeds of Zenn
+const EMBED_TYPES: [&str; 14] = [
+    "card",
+    "tweet",
+    "youtube",
+    "github",
+    "gist",
+    "codepen",
+    "codesandbox",
+    "stackblitz",
+    "jsfiddle",
+    "slideshare",
+    "speakerdeck",
+    "docswell",
+    "figma",
+    "blueprintue",
+];
+const MACRO_BEGIN: &str = "<macro>";
+const MACRO_END: &str = "</macro>";
+const IMAGE_LINK: &str = "](/images/";
+const ARTICLE_LINK: &str = "](zeta:";
+
+/// JSON-RPC error code of a message that isn't valid JSON
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code of an unknown request
+const METHOD_NOT_FOUND: i64 = -32601;
+/// `TextDocumentSyncKind.Full`
+const SYNC_FULL: u8 = 1;
+/// `DiagnosticSeverity.Error`
+const SEVERITY_ERROR: u8 = 1;
+/// `CompletionItemKind` values
+const KIND_FILE: u8 = 17;
+const KIND_FOLDER: u8 = 19;
+const KIND_ENUM_MEMBER: u8 = 20;
+
+/// Runs the language server on stdin and stdout until the client exits.
+pub fn run() {
+    let mut input = io::stdin().lock();
+    let mut server = Server::default();
+    while let Some(incoming) = read_message(&mut input) {
+        let message = match incoming {
+            Incoming::Message(message) => message,
+            Incoming::Invalid(id) => {
+                if let Some(id) = id {
+                    write_message(&json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": PARSE_ERROR, "message": "Invalid message" },
+                    }));
+                }
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err(message) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": METHOD_NOT_FOUND, "message": message },
+                    }),
+                };
+                write_message(&response);
+            }
+            None => server.notification(method, params),
+        }
+    }
+}
+
+/// A message from the client, or one that is skipped.
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Message(Value),
+    /// no valid Content-Length or JSON body, with the id of the request if it can be found
+    Invalid(Option<Value>),
+}
+
+/// Reads the next message. `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Option<Incoming> {
+    let mut length = None;
+    loop {
+        let mut header = Vec::new();
+        if input.read_until(b'\n', &mut header).ok()? == 0 {
+            return None;
+        }
+        let header = String::from_utf8_lossy(&header);
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    // without a length, the body is read as the headers of the next message and skipped too
+    let Some(length) = length else {
+        return Some(Incoming::Invalid(None));
+    };
+    let mut body = Vec::new();
+    input.take(length).read_to_end(&mut body).ok()?;
+    if body.len() as u64 != length {
+        return None;
+    }
+    Some(match serde_json::from_slice(&body) {
+        Ok(message) => Incoming::Message(message),
+        Err(_) => Incoming::Invalid(request_id(&body)),
+    })
+}
+
+/// The id of a request whose body isn't valid JSON, if it can be found.
+fn request_id(body: &[u8]) -> Option<Value> {
+    let body = String::from_utf8_lossy(body);
+    body.match_indices("\"id\"").find_map(|(start, key)| {
+        let value = body[start + key.len()..].trim_start().strip_prefix(':')?;
+        let id = serde_json::Deserializer::from_str(value)
+            .into_iter::<Value>()
+            .next()?
+            .ok()?;
+        (id.is_number() || id.is_string()).then_some(id)
+    })
+}
+
+fn write_message(message: &Value) {
+    let body = message.to_string();
+    let mut output = io::stdout().lock();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    output.flush().unwrap();
+}
+
+#[derive(Debug, Default)]
+struct Server {
+    /// open documents by URI
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, String> {
+        match method {
+            "initialize" => {
+                // includes and article links are resolved from the project root,
+                // whose URI is percent-encoded like every other
+                if let Some(root) = params["rootUri"].as_str() {
+                    let _ = std::env::set_current_dir(uri_path(root));
+                }
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": SYNC_FULL,
+                        "hoverProvider": true,
+                        "completionProvider": { "triggerCharacters": [" ", "[", "/"] },
+                        "definitionProvider": true,
+                    },
+                    "serverInfo": { "name": "zeta", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => Ok(Value::Null),
+            "textDocument/hover" => Ok(self
+                .cursor(params)
+                .and_then(|(_, text, offset)| hover(text, offset))
+                .map_or(
+                    Value::Null,
+                    |contents| json!({ "contents": { "kind": "markdown", "value": contents } }),
+                )),
+            "textDocument/completion" => Ok(self
+                .cursor(params)
+                .map_or(Value::Null, |(_, text, offset)| completion(text, offset))),
+            "textDocument/definition" => Ok(self
+                .cursor(params)
+                .and_then(|(uri, text, offset)| definition(uri, text, offset))
+                .unwrap_or(Value::Null)),
+            _ => Err(format!("Unknown method '{}'.", method)),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                else {
+                    return;
+                };
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return,
+        }
+
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map_or(Vec::new(), |text| diagnostics(text));
+        write_message(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }));
+    }
+
+    /// The document and the byte offset of the position in the parameters.
+    fn cursor<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a str, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some((uri, text, offset(text, line, character)?))
+    }
+}
+
+/// Scan and parse errors of a zeta article.
+fn diagnostics(text: &str) -> Vec<Value> {
+    let errors: Vec<(usize, usize, String)> = match Scanner::new(text.chars().collect()).scan_file()
+    {
+        Err(errors) => errors
+            .into_iter()
+            .map(|error| (error.row, error.col, error.error_type.to_string()))
+            .collect(),
+        Ok(markdown) => match parser::Parser::new(markdown)
+            .with_macros(project_macros())
+            .parse()
+        {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|error| {
+                    // rows of frontmatter errors start after the `---` line
+                    let row = match error.error_type {
+                        parser::ParseErrorType::InvalidFrontMatter if error.row > 0 => {
+                            error.row + 1
+                        }
+                        _ => error.row,
+                    };
+                    (row, error.col, error.error_type.to_string())
+                })
+                .collect(),
+        },
+    };
+
+    errors
+        .into_iter()
+        .map(|(row, col, message)| {
+            let line = row.saturating_sub(1);
+            let source = text.split('\n').nth(line).unwrap_or_default();
+            let start = utf16_len(source.chars().take(col.saturating_sub(1)));
+            json!({
+                "range": {
+                    "start": { "line": line, "character": start },
+                    "end": { "line": line, "character": utf16_len(source.chars()).max(start) },
+                },
+                "severity": SEVERITY_ERROR,
+                "source": "zeta",
+                "message": message,
+            })
+        })
+        .collect()
+}
+
+/// Both platform expansions of the `<macro>` or `{{name}}` under the cursor.
+fn hover(text: &str, offset: usize) -> Option<String> {
+    if let Some(range) = enclosing(text, offset, MACRO_BEGIN, MACRO_END) {
+        let yaml = &text[range.start + MACRO_BEGIN.len()..range.end - MACRO_END.len()];
+        let r#macro = serde_yaml::from_str::<StringMacro>(yaml).ok()?;
+        return Some(expansions(&r#macro));
+    }
+
+    let range = enclosing(text, offset, "{{", "}}")?;
+    let name = text[range.start + 2..range.end - 2].trim();
+    let mut macros = project_macros();
+    macros.extend(
+        frontmatter(text)
+            .map(|frontmatter| frontmatter.macros)
+            .unwrap_or_default(),
+    );
+    macros.get(name).map(expansions)
+}
+
+fn expansions(r#macro: &StringMacro) -> String {
+    let expansion = |text: &Option<String>| text.clone().unwrap_or_default();
+    format!(
+        "**Zenn**\n```markdown\n{}\n```\n**Qiita**\n```markdown\n{}\n```",
+        expansion(&r#macro.zenn),
+        expansion(&r#macro.qiita)
+    )
+}
+
+/// Message types, embed types and image paths for the text before the cursor.
+fn completion(text: &str, offset: usize) -> Value {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &text[line_start..offset];
+
+    let is_word = |text: &str| text.chars().all(|c| c.is_ascii_alphanumeric());
+    let items: Vec<Value> = if let Some(partial) = prefix
+        .trim_start()
+        .strip_prefix(":::")
+        .map(|rest| rest.trim_start_matches(':'))
+        .and_then(|rest| rest.strip_prefix("message "))
+    {
+        if !is_word(partial.trim_start()) {
+            return Value::Null;
+        }
+        MESSAGE_TYPES
+            .iter()
+            .map(|label| json!({ "label": label, "kind": KIND_ENUM_MEMBER }))
+            .collect()
+    } else if prefix
+        .rfind("@[")
+        .is_some_and(|i| is_word(&prefix[i + 2..]))
+    {
+        EMBED_TYPES
+            .iter()
+            .map(|label| json!({ "label": label, "kind": KIND_ENUM_MEMBER }))
+            .collect()
+    } else if let Some(i) = prefix.rfind(IMAGE_LINK) {
+        let path = &prefix[i + IMAGE_LINK.len()..];
+        if path.contains([')', ' ']) {
+            return Value::Null;
+        }
+        let directory = Path::new("images").join(&path[..path.rfind('/').map_or(0, |i| i + 1)]);
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Value::Null;
+        };
+        let mut items: Vec<(String, bool)> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                Some((
+                    entry.file_name().to_str()?.to_string(),
+                    entry.path().is_dir(),
+                ))
+            })
+            .collect();
+        items.sort();
+        items
+            .into_iter()
+            .map(|(name, is_dir)| {
+                let kind = if is_dir { KIND_FOLDER } else { KIND_FILE };
+                json!({ "label": name, "kind": kind })
+            })
+            .collect()
+    } else {
+        return Value::Null;
+    };
+    Value::Array(items)
+}
+
+/// The definition of the footnote or the article linked under the cursor.
+fn definition(uri: &str, text: &str, offset: usize) -> Option<Value> {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line = &text[line_start..line_end];
+    let cursor = offset - line_start;
+
+    // [^name]
+    for (start, _) in line.match_indices("[^") {
+        let Some(end) = line[start..].find(']').map(|end| start + end) else {
+            continue;
+        };
+        if !(start..=end).contains(&cursor) {
+            continue;
+        }
+        let name = &line[start + 2..end];
+        let tokens = Scanner::new(text.chars().collect())
+            .scan_file()
+            .ok()?
+            .elements;
+        let mut references = Vec::new();
+        let mut definitions = Vec::new();
+        collect_footnotes(&tokens, &mut references, &mut definitions);
+        // a `[^name]` in code is neither a reference nor a definition
+        let row = text[..offset].matches('\n').count() + 1;
+        let footnote = (name.to_string(), row);
+        if !references.contains(&footnote) && !definitions.contains(&footnote) {
+            continue;
+        }
+        let (_, row) = definitions
+            .into_iter()
+            .find(|(definition, _)| definition == name)?;
+        return Some(location(uri, row - 1));
+    }
+
+    // [text](zeta:slug)
+    for (start, _) in line.match_indices(ARTICLE_LINK) {
+        let Some(end) = line[start..].find(')').map(|end| start + end) else {
+            continue;
+        };
+        let text_start = line[..start].rfind('[').unwrap_or(start);
+        if !(text_start..=end).contains(&cursor) {
+            continue;
+        }
+        let slug = &line[start + ARTICLE_LINK.len()..end];
+        let path = fs::canonicalize(format!("zeta/{}.md", slug)).ok()?;
+        return Some(location(&file_uri(&path), 0));
+    }
+    None
+}
+
+/// Collects the names and rows of the footnote references and definitions.
+fn collect_footnotes(
+    tokens: &[Token],
+    references: &mut Vec<(String, usize)>,
+    definitions: &mut Vec<(String, usize)>,
+) {
+    for token in tokens {
+        match &token.token_type {
+            TokenType::Footnote(name) => references.push((name.clone(), token.row)),
+            TokenType::FootnoteDefinition { name, body } => {
+                // the body starts with the `[^name]:` marker
+                let row = body.first().map_or(token.row, |marker| marker.row);
+                definitions.push((name.clone(), row));
+                collect_footnotes(body, references, definitions);
+            }
+            TokenType::InlineFootnote(body)
+            | TokenType::Conditional { body, .. }
+            | TokenType::Heading { text: body, .. }
+            | TokenType::ArticleLink { text: body, .. } => {
+                collect_footnotes(body, references, definitions)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn location(uri: &str, line: usize) -> Value {
+    json!({
+        "uri": uri,
+        "range": {
+            "start": { "line": line, "character": 0 },
+            "end": { "line": line, "character": 0 },
+        },
+    })
+}
+
+/// Range of the innermost `open`...`close` around the offset.
+fn enclosing(text: &str, offset: usize, open: &str, close: &str) -> Option<Range<usize>> {
+    let start = text
+        .match_indices(open)
+        .map(|(start, _)| start)
+        .take_while(|start| *start <= offset)
+        .last()?;
+    let end = start + open.len() + text[start + open.len()..].find(close)? + close.len();
+    (offset < end).then_some(start..end)
+}
+
+fn project_macros() -> BTreeMap<String, StringMacro> {
+    fs::read_to_string(MACROS_FILE)
+        .ok()
+        .and_then(|macros| serde_yaml::from_str(&macros).ok())
+        .unwrap_or_default()
+}
+
+fn frontmatter(text: &str) -> Option<ZetaFrontmatter> {
+    let text = text.trim_start().strip_prefix("---\n")?;
+    let end = text.find("---\n")?;
+    serde_yaml::from_str(&text[..end]).ok()
+}
+
+/// Byte offset of an LSP position, which counts UTF-16 code units.
+fn offset(text: &str, line: usize, character: usize) -> Option<usize> {
+    let line_start = if line == 0 {
+        0
+    } else {
+        text.match_indices('\n').nth(line - 1)?.0 + 1
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+fn utf16_len(chars: impl Iterator<Item = char>) -> usize {
+    chars.map(char::len_utf16).sum()
+}
+
+/// `file://` URI of an absolute path, percent-encoded except for the unreserved characters.
+fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI.
+fn uri_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_framed_by_content_length() {
+        let body = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let input = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}{}",
+            body.len(),
+            body,
+            "Content-Length: 2\r\n\r\n{}"
+        );
+        let mut input = input.as_bytes();
+        assert_eq!(
+            read_message(&mut input),
+            Some(Incoming::Message(
+                json!({ "jsonrpc": "2.0", "method": "exit" })
+            ))
+        );
+        assert_eq!(read_message(&mut input), Some(Incoming::Message(json!({}))));
+        assert_eq!(read_message(&mut input), None);
+
+        // a body cut short ends the input
+        assert_eq!(
+            read_message(&mut "Content-Length: 10\r\n\r\n{}".as_bytes()),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_messages_are_skipped() {
+        let mut input = "Content-Length: x\r\n\r\n\
+            Content-Length: 26\r\n\r\n{\"id\": 3, \"method\": \"a\", }\
+            Content-Length: 1\r\n\r\n{\
+            Content-Length: 2\r\n\r\n{}"
+            .as_bytes();
+        assert_eq!(read_message(&mut input), Some(Incoming::Invalid(None)));
+        assert_eq!(
+            read_message(&mut input),
+            Some(Incoming::Invalid(Some(json!(3))))
+        );
+        assert_eq!(read_message(&mut input), Some(Incoming::Invalid(None)));
+        assert_eq!(read_message(&mut input), Some(Incoming::Message(json!({}))));
+
+        assert_eq!(
+            request_id(br#"{"params": {"id": {}}, "id": "a", "#),
+            Some(json!("a"))
+        );
+    }
+
+    #[test]
+    fn uris_are_percent_encoded() {
+        let path = Path::new("/home/me/My Project/記事 #1.md");
+        let uri = "file:///home/me/My%20Project/%E8%A8%98%E4%BA%8B%20%231.md";
+        assert_eq!(file_uri(path), uri);
+        assert_eq!(uri_path(uri), path);
+        assert_eq!(uri_path("file:///a%2"), Path::new("/a%2"));
+    }
+
+    #[test]
+    fn positions_count_utf16_code_units() {
+        let text = "aé\n😀b\nc";
+        assert_eq!(utf16_len("😀b".chars()), 3);
+        assert_eq!(offset(text, 0, 0), Some(0));
+        assert_eq!(offset(text, 0, 2), Some(3));
+        assert_eq!(offset(text, 1, 0), Some(4));
+        assert_eq!(offset(text, 1, 2), Some(8));
+        assert_eq!(offset(text, 1, 3), Some(9));
+        // past the end of a line is its end
+        assert_eq!(offset(text, 0, 10), Some(3));
+        assert_eq!(offset(text, 2, 1), Some(text.len()));
+        assert_eq!(offset(text, 3, 0), None);
+    }
+
+    #[test]
+    fn footnotes_in_code_are_not_definitions() {
+        let text = "---\ntitle: t\n---\na[^a]\n\n```\n[^a]: code\n```\n\n[^a]: note\n\n`[^a]`\n";
+        let line = |offset| {
+            definition("file:///a.md", text, offset)
+                .map(|location| location["range"]["start"]["line"].clone())
+        };
+        let reference = text.find("[^a]").unwrap() + 1;
+        assert_eq!(line(reference), Some(json!(9)));
+        assert_eq!(line(text.rfind("[^a]").unwrap() + 1), None);
+    }
+}
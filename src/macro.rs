@@ -1,6 +1,6 @@
 use crate::{ast::Element, token::Token};
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
 pub enum Platform {
     #[serde(alias = "zenn")]
     Zenn,
@@ -12,7 +12,7 @@ pub type StringMacro = Macro<Option<String>>;
 pub type TokenizedMacro = Macro<Vec<Token>>;
 pub type ParsedMacro = Macro<Vec<Element>>;
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct Macro<T> {
     pub zenn: T,
     pub qiita: T,
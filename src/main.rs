@@ -1,27 +1,41 @@
-use ast::{ParsedMd, ZetaFrontmatter};
-use clap::{command, Parser, Subcommand};
-use compiler::{QiitaCompiler, QiitaFrontmatter, ZennCompiler};
+use ast::{ArticleType, ParsedMd, TokenizedMd, ZetaFrontmatter};
+use clap::{Parser, Subcommand};
+use compiler::{CompileError, QiitaCompiler, ZennCompiler};
 use print::{zeta_error, zeta_error_position};
-use r#macro::Platform;
+use r#macro::{Platform, StringMacro};
 use scanner::Scanner;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::{
+    collections::BTreeMap,
     fs::{self, DirBuilder},
     io::Write,
     process::Command,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use crate::print::zeta_message;
+use crate::print::{zeta_message, zeta_warning};
 
+mod article;
 mod ast;
+mod book;
+mod checker;
 mod compiler;
+mod formatter;
+mod lsp;
 mod r#macro;
+mod markdown;
+mod mermaid;
 mod parser;
 mod print;
 mod scanner;
+mod schedule;
+mod series;
+mod toc;
 mod token;
 
+/// project-wide macro definitions
+const MACROS_FILE: &str = "macros.yaml";
+
 #[derive(Debug, Clone, clap::Parser)]
 #[command(version, about)]
 struct Cli {
@@ -40,40 +54,139 @@ enum ZetaCommand {
         #[arg(long)]
         only: Option<Platform>,
     },
+    /// Manage Zenn books
+    Book {
+        #[command(subcommand)]
+        command: BookCommand,
+    },
     /// Build article
-    Build { target: String },
+    Build {
+        #[arg(required_unless_present = "book")]
+        target: Option<String>,
+        /// Build a book in zeta/books/ instead of an article
+        #[arg(long, conflicts_with = "target")]
+        book: Option<String>,
+        /// Pre-render mermaid diagrams to SVG images for platforms that can't display them
+        #[arg(long, value_delimiter = ',')]
+        render_mermaid: Vec<Platform>,
+    },
+    /// Check article without building
+    Check { target: String },
+    /// Format articles (all articles in zeta/ if none is given)
+    Fmt {
+        targets: Vec<String>,
+        /// Report unformatted articles without rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Start the language server on stdio
+    Lsp,
     /// Rename article
     Rename { target: String, new_name: String },
     /// Remove article
     Remove { target: String },
 }
 
+#[derive(Debug, Clone, Subcommand)]
+enum BookCommand {
+    /// Create new book
+    New { slug: String },
+}
+
 fn main() {
     let cli = Cli::parse();
     match cli.command {
         ZetaCommand::Init => init(),
         ZetaCommand::New { target, only } => new(&target, &only),
-        ZetaCommand::Build { target } => build(&target),
+        ZetaCommand::Book { command } => match command {
+            BookCommand::New { slug } => book::new_book(&slug),
+        },
+        ZetaCommand::Build {
+            target,
+            book,
+            render_mermaid,
+        } => {
+            let ok = match (target, book) {
+                (_, Some(book)) => book::build_book(&book, &render_mermaid),
+                (Some(target), None) => build(&target, &render_mermaid),
+                (None, None) => unreachable!(),
+            };
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+        ZetaCommand::Check { target } => check(&target),
+        ZetaCommand::Fmt { targets, check } => fmt(&targets, check),
+        ZetaCommand::Lsp => lsp::run(),
         ZetaCommand::Rename { target, new_name } => rename(&target, &new_name),
         ZetaCommand::Remove { target } => remove(&target),
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 struct Settings {
     repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zenn_user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    qiita_user: Option<String>,
+    /// default Zenn Publication of the articles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publication_name: Option<String>,
+    /// default Qiita Organization of new articles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    organization_url_name: Option<String>,
+    /// whether articles are Qiita slide decks unless they set `qiita.slide`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slide: Option<bool>,
+    /// Zenn topic to platform-specific tag names
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, TagMapping>,
 }
 
-fn init() {
-    zeta_message("Zeta init");
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct TagMapping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    qiita: Option<String>,
+}
+
+fn load_settings() -> Settings {
+    let Ok(settings) = fs::read_to_string("./Zeta.toml") else {
+        return Settings::default();
+    };
+    match toml::from_str(settings.as_str()) {
+        Ok(settings) => settings,
+        Err(error) => {
+            zeta_error(&format!("Failed to parse Zeta.toml: {}", error));
+            Settings::default()
+        }
+    }
+}
 
-    print!("GitHub Repository(User/Repo): ");
+fn read_line(prompt: &str) -> String {
+    print!("{}", prompt);
     std::io::stdout().flush().unwrap();
-    let mut repository = String::new();
-    std::io::stdin().read_line(&mut repository).unwrap();
-    repository = repository.trim().to_string();
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).unwrap();
+    line.trim().to_string()
+}
 
-    let settings = Settings { repository };
+fn init() {
+    zeta_message("Zeta init");
+
+    let repository = read_line("GitHub Repository(User/Repo): ");
+    let zenn_user = Some(read_line("Zenn Username (optional): ")).filter(|user| !user.is_empty());
+    let qiita_user = Some(read_line("Qiita Username (optional): ")).filter(|user| !user.is_empty());
+
+    let settings = Settings {
+        repository,
+        zenn_user,
+        qiita_user,
+        publication_name: None,
+        organization_url_name: None,
+        slide: None,
+        tags: BTreeMap::new(),
+    };
 
     zeta_message("Creating Zeta.toml...");
     fs::File::create("Zeta.toml")
@@ -142,11 +255,17 @@ fn new(target: &str, only: &Option<Platform>) {
     let frontmatter = ZetaFrontmatter {
         title: "".to_string(),
         emoji: "😀".to_string(),
-        r#type: "tech".to_string(),
+        r#type: ArticleType::Tech,
         topics: vec![],
         qiita_id: Some("".to_string()),
         published: false,
+        published_at: None,
         only: only.clone(),
+        macros: BTreeMap::new(),
+        toc: false,
+        series: None,
+        zenn: None,
+        qiita: None,
     };
     file.write_all(b"---\n").unwrap();
     let mut serializer = serde_yaml::Serializer::new(&mut file);
@@ -154,69 +273,283 @@ fn new(target: &str, only: &Option<Platform>) {
     file.write_all(b"---\n").unwrap();
 }
 
-fn build(target: &str) {
-    let target = &PathBuf::from(target).file_stem().unwrap().to_os_string().into_string().unwrap();
+fn load(target: &str) -> Option<ParsedMd> {
     let Ok(file) = fs::read_to_string(format!("zeta/{}.md", target)) else {
         zeta_error("Target not found");
-        return;
+        return None;
+    };
+
+    parse_article(&file)
+}
+
+/// Scans and parses the source of an article, reporting the errors.
+fn parse_article(file: &str) -> Option<ParsedMd> {
+    let markdown = scan(file)?;
+    let macros = load_macros()?;
+    report_parse_errors(parser::Parser::new(markdown).with_macros(macros).parse())
+}
+
+/// Whether the source of an article scans and parses, without reporting its errors.
+fn parses(file: &str) -> bool {
+    let Ok(markdown) = Scanner::new(file.chars().collect()).scan_file() else {
+        return false;
     };
+    let Some(macros) = load_macros() else {
+        return false;
+    };
+    parser::Parser::new(markdown).with_macros(macros).parse().is_ok()
+}
 
+/// The article name of a target given as a name or as a path like `zeta/name.md`.
+fn target_slug(target: &str) -> Option<String> {
+    let slug = Path::new(target).file_stem()?.to_str()?;
+    Some(slug.to_string())
+}
+
+fn scan(file: &str) -> Option<TokenizedMd> {
     let scanner = Scanner::new(file.chars().collect());
-    let markdown = match scanner.scan_file() {
-        Ok(file) => file,
+    match scanner.scan_file() {
+        Ok(file) => Some(file),
         Err(errors) => {
             errors.iter().for_each(|error| {
                 zeta_error_position(&error.error_type.to_string(), error.row, error.col);
             });
-            return;
+            None
         }
-    };
+    }
+}
+
+fn load_macros() -> Option<BTreeMap<String, StringMacro>> {
+    match fs::read_to_string(MACROS_FILE) {
+        Ok(macros) => match serde_yaml::from_str::<BTreeMap<String, StringMacro>>(&macros) {
+            Ok(macros) => Some(macros),
+            Err(error) => {
+                zeta_error(&format!("Failed to parse {}: {}", MACROS_FILE, error));
+                None
+            }
+        },
+        Err(_) => Some(BTreeMap::new()),
+    }
+}
 
-    let parser = parser::Parser::new(markdown);
-    let file = match parser.parse() {
-        Ok(file) => file,
+fn report_parse_errors(
+    result: Result<ParsedMd, Vec<parser::ParseError>>,
+) -> Option<ParsedMd> {
+    match result {
+        Ok(file) => Some(file),
         Err(errors) => {
             errors.iter().for_each(|error| {
                 zeta_error_position(&error.error_type.to_string(), error.row, error.col);
             });
-            return;
+            None
         }
+    }
+}
+
+/// Builds an article for its platforms. Returns whether every output was written.
+fn build(target: &str, render_mermaid: &[Platform]) -> bool {
+    let target = &PathBuf::from(target).file_stem().unwrap().to_os_string().into_string().unwrap();
+    let Some(file) = load(target) else {
+        return false;
     };
 
-    if let Some(platform) = &file.frontmatter.only {
-        match platform {
-            Platform::Zenn => compile_zenn(file, target),
-            Platform::Qiita => compile_qiita(file, target),
-        }
-    } else {
-        compile_zenn(file.clone(), target);
-        compile_qiita(file, target);
+    let platforms = match &file.frontmatter.only {
+        Some(platform) => vec![platform.clone()],
+        None => vec![Platform::Zenn, Platform::Qiita],
+    };
+    // diagrams are only replaced in the outputs they were requested for
+    let rendered = platforms
+        .iter()
+        .any(|platform| render_mermaid.contains(platform))
+        .then(|| mermaid::render_mermaid(file.clone(), target));
+    // the other parts are read once for both platforms
+    let series = match &file.frontmatter.series {
+        Some(_) => series::Index::read(),
+        None => series::Index::default(),
+    };
+
+    let mut ok = true;
+    for platform in platforms {
+        let file = match &rendered {
+            Some(rendered) if render_mermaid.contains(&platform) => rendered.clone(),
+            _ => file.clone(),
+        };
+        ok &= match platform {
+            Platform::Zenn => compile_zenn(file, target, &series),
+            Platform::Qiita => compile_qiita(file, target, &series),
+        };
     }
+    ok
 }
 
-fn compile_zenn(file: ParsedMd, target: &str) {
-    let compiler = ZennCompiler::new();
-    let zenn_md = compiler.compile(file);
-    fs::write(format!("articles/{}.md", target), zenn_md).unwrap();
+fn check(target: &str) {
+    let target = &PathBuf::from(target).file_stem().unwrap().to_os_string().into_string().unwrap();
+    let Some(file) = load(target) else {
+        return;
+    };
+
+    let zenn = !matches!(file.frontmatter.only, Some(Platform::Qiita));
+    let qiita = !matches!(file.frontmatter.only, Some(Platform::Zenn));
+    let zenn_ok = !zenn || check_zenn(&file, target, &load_settings());
+    let qiita_ok = !qiita
+        || match article::read_qiita_frontmatter(target) {
+            Ok(existing_header) => {
+                check_qiita(&file, &QiitaCompiler::new(target, load_settings(), existing_header))
+            }
+            Err(error) => {
+                zeta_error(&error);
+                false
+            }
+        };
+    if !(zenn_ok && qiita_ok) {
+        return;
+    }
+
+    checker::check_tags(&file.frontmatter, &load_settings())
+        .iter()
+        .for_each(|warning| zeta_warning(warning));
+
+    zeta_message("No problems found");
 }
 
-fn compile_qiita(file: ParsedMd, target: &str) {
-    let existing_header =
-        if let Ok(existing_file) = fs::read_to_string(format!("public/{}.md", target)) {
-            let existing_file = &existing_file[4..];
-            let end = existing_file.find("---").unwrap();
-            let existing_file = &existing_file[..end];
-            let de = serde_yaml::Deserializer::from_str(existing_file);
-            Some(QiitaFrontmatter::deserialize(de).unwrap())
+fn fmt(targets: &[String], check: bool) {
+    let mut ok = true;
+    let targets: Vec<String> = if targets.is_empty() {
+        article::slugs()
+    } else {
+        targets
+            .iter()
+            .filter_map(|target| {
+                let slug = target_slug(target);
+                if slug.is_none() {
+                    zeta_error(&format!("Invalid target '{}'", target));
+                    ok = false;
+                }
+                slug
+            })
+            .collect()
+    };
+
+    for target in targets {
+        let path = format!("zeta/{}.md", target);
+        let Ok(source) = fs::read_to_string(&path) else {
+            zeta_error(&format!("{} not found", path));
+            ok = false;
+            continue;
+        };
+        if scan(&source).is_none() {
+            ok = false;
+            continue;
+        }
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                zeta_error(&format!("{}: {}", path, error));
+                ok = false;
+                continue;
+            }
+        };
+        // the article is checked after formatting, which fixes the colons of mis-nested `:::` blocks.
+        // Only articles that build are formatted, and the errors are reported where they are written.
+        if !parses(&formatted) {
+            if parse_article(&source).is_some() {
+                zeta_error(&format!("{} does not build after formatting", path));
+            }
+            ok = false;
+            continue;
+        }
+        if formatted == source {
+            continue;
+        }
+        if check {
+            zeta_error(&format!("{} is not formatted", path));
+            ok = false;
         } else {
+            fs::write(&path, formatted).unwrap();
+            zeta_message(&format!("Formatted {}", path));
+        }
+    }
+
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+fn check_zenn(file: &ParsedMd, target: &str, settings: &Settings) -> bool {
+    match checker::check_zenn(file, target, settings) {
+        Ok(()) => true,
+        Err(errors) => {
+            errors.iter().for_each(|error| zeta_error(&error.to_string()));
+            false
+        }
+    }
+}
+
+fn check_qiita(file: &ParsedMd, compiler: &QiitaCompiler) -> bool {
+    let frontmatter = compiler.qiita_frontmatter(file.frontmatter.clone().for_qiita());
+    match checker::check_qiita(file, &frontmatter) {
+        Ok(()) => true,
+        Err(errors) => {
+            errors.iter().for_each(|error| zeta_error(&error.to_string()));
+            false
+        }
+    }
+}
+
+fn report_compile_errors(result: Result<String, Vec<CompileError>>) -> Option<String> {
+    match result {
+        Ok(markdown) => Some(markdown),
+        Err(errors) => {
+            errors.iter().for_each(|error| zeta_error(&error.to_string()));
             None
-        };
+        }
+    }
+}
+
+fn compile_zenn(mut file: ParsedMd, target: &str, series: &series::Index) -> bool {
+    let settings = load_settings();
+    if !check_zenn(&file, target, &settings) {
+        return false;
+    }
+    let navigation = series.navigation(target, &file.frontmatter, Platform::Zenn);
+    file.elements.extend(navigation);
+
+    let compiler = ZennCompiler::new(target, settings);
+    let Some(zenn_md) = report_compile_errors(compiler.compile(file)) else {
+        return false;
+    };
+    fs::write(format!("articles/{}.md", target), zenn_md).unwrap();
+    true
+}
+
+fn compile_qiita(mut file: ParsedMd, target: &str, series: &series::Index) -> bool {
+    let settings = load_settings();
+    checker::check_tags(&file.frontmatter, &settings)
+        .iter()
+        .for_each(|warning| zeta_warning(warning));
+
+    // a broken header in public/ would lose the Qiita id when overwritten
+    let existing_header = match article::read_qiita_frontmatter(target) {
+        Ok(existing_header) => existing_header,
+        Err(error) => {
+            zeta_error(&error);
+            return false;
+        }
+    };
+    let compiler = QiitaCompiler::new(target, settings, existing_header);
+    if !check_qiita(&file, &compiler) {
+        return false;
+    }
+    let navigation = series.navigation(target, &file.frontmatter, Platform::Qiita);
+    file.elements.extend(navigation);
 
-    let compiler = QiitaCompiler::new(existing_header);
-    let qiita_md = compiler.compile(file.clone());
+    let Some(qiita_md) = report_compile_errors(compiler.compile(file)) else {
+        return false;
+    };
 
     DirBuilder::new().recursive(true).create("public").unwrap();
     fs::write(format!("public/{}.md", target), qiita_md).unwrap();
+    true
 }
 
 fn rename(target: &str, new_name: &str) {
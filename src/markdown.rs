@@ -0,0 +1,1372 @@
+use crate::ast::{Alignment, Element};
+
+/// width from which a line starts an indented code block
+const CODE_INDENT: usize = 4;
+/// maximum number of digits of an ordered list marker
+const LIST_NUMBER_DIGITS_MAX: usize = 9;
+
+/// tags that start an HTML block even with text after them
+const HTML_BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+/// A line of the element stream. Zeta elements stay as items between the text pieces.
+#[derive(Debug, Clone, Default)]
+struct Line {
+    items: Vec<Element>,
+    /// ends with `\n`
+    terminated: bool,
+}
+
+impl Line {
+    /// index of the first item after the markers of the enclosing blocks
+    fn content_start(&self) -> usize {
+        self.items
+            .iter()
+            .take_while(|item| matches!(item, Element::Trivia(_)))
+            .count()
+    }
+
+    /// markers of the enclosing blocks
+    fn trivia(&self) -> Vec<Element> {
+        self.items[..self.content_start()].to_vec()
+    }
+
+    /// text before the first zeta element
+    fn head(&self) -> &str {
+        match self.items.get(self.content_start()) {
+            Some(Element::Text(text)) => text,
+            _ => "",
+        }
+    }
+
+    fn indent(&self) -> usize {
+        self.head().chars().take_while(|c| *c == ' ').count()
+    }
+
+    fn is_blank(&self) -> bool {
+        self.items.iter().all(|item| match item {
+            Element::Text(text) => text.trim().is_empty(),
+            Element::Trivia(_) => true,
+            _ => false,
+        })
+    }
+
+    fn is_text(&self) -> bool {
+        self.items
+            .iter()
+            .all(|item| matches!(item, Element::Text(_) | Element::Trivia(_)))
+    }
+
+    fn has_block_element(&self) -> bool {
+        self.items.iter().any(is_block_element)
+    }
+
+    /// the only zeta element on the line, apart from spaces
+    fn is_standalone(&self) -> bool {
+        let mut elements = self.items.iter().filter(|item| match item {
+            Element::Text(text) => !text.trim().is_empty(),
+            Element::Trivia(_) => false,
+            _ => true,
+        });
+        matches!(elements.next(), Some(element) if !matches!(element, Element::Text(_)))
+            && elements.next().is_none()
+    }
+
+    /// text of the line without the markers of the enclosing blocks
+    fn text(&self) -> String {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                Element::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// the line as written
+    fn source(&self) -> String {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                Element::Text(text) | Element::Trivia(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Turns the first `count` characters of the leading text into trivia.
+    fn strip(mut self, count: usize) -> Line {
+        let start = self.content_start();
+        let Some(Element::Text(text)) = self.items.get_mut(start) else {
+            return self;
+        };
+        let stripped: String = text.chars().take(count).collect();
+        *text = text.chars().skip(count).collect();
+        if text.is_empty() {
+            self.items.remove(start);
+        }
+        if stripped.is_empty() {
+            return self;
+        }
+        match start.checked_sub(1).map(|i| &mut self.items[i]) {
+            Some(Element::Trivia(trivia)) => trivia.push_str(&stripped),
+            _ => self.items.insert(start, Element::Trivia(stripped)),
+        }
+        self
+    }
+}
+
+/// Zeta elements that always take whole lines.
+fn is_block_element(element: &Element) -> bool {
+    matches!(
+        element,
+        Element::Message { .. }
+            | Element::Details { .. }
+            | Element::Mermaid { .. }
+            | Element::Heading { .. }
+            | Element::Toc
+            | Element::SlideBreak { .. }
+            | Element::FootnoteDefinition { .. }
+    )
+}
+
+/// Structures a body into CommonMark blocks. The line breaks between blocks are kept as text,
+/// so the backends reproduce the spacing of the source.
+pub fn blocks(elements: Vec<Element>) -> Vec<Element> {
+    parse_lines(split_lines(elements))
+}
+
+fn split_lines(elements: Vec<Element>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut items = Vec::new();
+    for element in elements {
+        match element {
+            Element::Text(text) => {
+                for (i, part) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        lines.push(Line {
+                            items: std::mem::take(&mut items),
+                            terminated: true,
+                        });
+                    }
+                    if !part.is_empty() {
+                        items.push(Element::Text(part.to_string()));
+                    }
+                }
+            }
+            // the scanner consumes the line break after a footnote definition
+            element @ Element::FootnoteDefinition { .. } => {
+                items.push(element);
+                lines.push(Line {
+                    items: std::mem::take(&mut items),
+                    terminated: false,
+                });
+            }
+            element => items.push(element),
+        }
+    }
+    if !items.is_empty() {
+        lines.push(Line {
+            items,
+            terminated: false,
+        });
+    }
+    lines
+}
+
+fn parse_lines(lines: Vec<Line>) -> Vec<Element> {
+    let mut elements = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (block, end) = parse_block(&lines, i);
+        elements.extend(block);
+        if lines[end - 1].terminated {
+            elements.push(Element::Text("\n".to_string()));
+        }
+        i = end;
+    }
+    merge_text(elements)
+}
+
+/// Parses the block starting at `lines[i]` and returns it with the index of the next line.
+fn parse_block(lines: &[Line], i: usize) -> (Vec<Element>, usize) {
+    let line = &lines[i];
+
+    if line.is_blank() || line.has_block_element() || line.is_standalone() {
+        let items = line
+            .items
+            .iter()
+            .cloned()
+            .map(|item| structure(item, true))
+            .collect();
+        return (items, i + 1);
+    }
+
+    if line.indent() >= CODE_INDENT && line.is_text() {
+        return parse_indented_code(lines, i);
+    }
+    if line.is_text() {
+        if let Some(fence) = fence_open(line.head()) {
+            return parse_fenced_code(lines, i, fence);
+        }
+        if is_thematic_break(line.head()) {
+            let mut block = line.trivia();
+            block.push(Element::ThematicBreak(line.head().to_string()));
+            return (block, i + 1);
+        }
+    }
+    if is_quote(line) {
+        return parse_quote(lines, i);
+    }
+    if let Some(marker) = list_marker(line) {
+        return parse_list(lines, i, marker);
+    }
+    if let Some(table) = parse_table(lines, i) {
+        return table;
+    }
+    if is_html_start(line) {
+        let mut end = i + 1;
+        while end < lines.len() && !lines[end].is_blank() {
+            end += 1;
+        }
+        return (vec![Element::Html(join(&lines[i..end]))], end);
+    }
+
+    parse_paragraph(lines, i)
+}
+
+fn parse_paragraph(lines: &[Line], i: usize) -> (Vec<Element>, usize) {
+    let mut end = i + 1;
+    while end < lines.len() {
+        let line = &lines[end];
+        if line.is_blank() {
+            break;
+        }
+        if let Some(level) = setext_level(line) {
+            let mut text = inlines(join(&lines[i..end]));
+            text.push(Element::Trivia(format!("\n{}", line.source())));
+            return (vec![Element::Heading { level, text }], end + 1);
+        }
+        if interrupts_paragraph(line) {
+            break;
+        }
+        end += 1;
+    }
+
+    (vec![Element::Paragraph(inlines(join(&lines[i..end])))], end)
+}
+
+fn interrupts_paragraph(line: &Line) -> bool {
+    if line.has_block_element() || is_quote(line) {
+        return true;
+    }
+    if line.is_text() && (fence_open(line.head()).is_some() || is_thematic_break(line.head())) {
+        return true;
+    }
+    // only non-empty items starting with 1 interrupt a paragraph
+    list_marker(line).is_some_and(|marker| !marker.empty && marker.start.unwrap_or(1) == 1)
+}
+
+/// Joins the lines as written, without the break of the last line.
+fn source(lines: &[Line]) -> String {
+    lines
+        .iter()
+        .map(Line::source)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Joins the items of the lines with line breaks, without the break of the last line.
+fn join(lines: &[Line]) -> Vec<Element> {
+    let mut elements = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            elements.push(Element::Text("\n".to_string()));
+        }
+        elements.extend(line.items.iter().cloned());
+    }
+    merge_text(elements)
+}
+
+fn parse_indented_code(lines: &[Line], i: usize) -> (Vec<Element>, usize) {
+    let mut end = i + 1;
+    while end < lines.len()
+        && (lines[end].is_blank() || lines[end].indent() >= CODE_INDENT && lines[end].is_text())
+    {
+        end += 1;
+    }
+    while lines[end - 1].is_blank() {
+        end -= 1;
+    }
+
+    (vec![Element::CodeBlock(source(&lines[i..end]))], end)
+}
+
+struct Fence {
+    char: char,
+    len: usize,
+}
+
+fn fence_open(head: &str) -> Option<Fence> {
+    let indent = head.chars().take_while(|c| *c == ' ').count();
+    let rest = &head[indent..];
+    let char = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.chars().take_while(|c| *c == char).count();
+    let info = rest[len..].trim();
+    if indent >= CODE_INDENT || len < 3 || char == '`' && info.contains('`') {
+        return None;
+    }
+    Some(Fence { char, len })
+}
+
+fn is_fence_close(line: &Line, fence: &Fence) -> bool {
+    let text = line.text();
+    let text = text.trim();
+    line.is_text()
+        && line.indent() < CODE_INDENT
+        && text.chars().count() >= fence.len
+        && text.chars().all(|c| c == fence.char)
+}
+
+fn parse_fenced_code(lines: &[Line], i: usize, fence: Fence) -> (Vec<Element>, usize) {
+    let mut end = i + 1;
+    while end < lines.len() && !is_fence_close(&lines[end], &fence) {
+        end += 1;
+    }
+    let next = (end + 1).min(lines.len());
+
+    // zeta elements inside `~~~` are kept as they are
+    if !lines[i + 1..end].iter().all(Line::is_text) {
+        return (join(&lines[i..next]), next);
+    }
+    (vec![Element::CodeBlock(source(&lines[i..next]))], next)
+}
+
+fn is_thematic_break(head: &str) -> bool {
+    let text: String = head.chars().filter(|c| *c != ' ').collect();
+    let indent = head.chars().take_while(|c| *c == ' ').count();
+    indent < CODE_INDENT
+        && text.chars().count() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|c| text.chars().all(|char| char == *c))
+}
+
+fn setext_level(line: &Line) -> Option<usize> {
+    let text = line.text();
+    let text = text.trim_end();
+    if !line.is_text() || line.indent() >= CODE_INDENT || text.trim().is_empty() {
+        return None;
+    }
+    let text = text.trim_start();
+    if text.chars().all(|c| c == '=') {
+        Some(1)
+    } else if text.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_quote(line: &Line) -> bool {
+    line.indent() < CODE_INDENT && line.head().trim_start().starts_with('>')
+}
+
+fn parse_quote(lines: &[Line], i: usize) -> (Vec<Element>, usize) {
+    let mut body: Vec<Line> = Vec::new();
+    let mut end = i;
+    while end < lines.len() {
+        let line = &lines[end];
+        if is_quote(line) {
+            let marker = line.indent() + 1;
+            let space = line.head().chars().nth(marker) == Some(' ');
+            body.push(line.clone().strip(marker + usize::from(space)));
+        } else if !line.is_blank()
+            && !interrupts_paragraph(line)
+            && body.last().is_some_and(|line| !line.is_blank())
+        {
+            // lazy continuation of a paragraph
+            body.push(line.clone());
+        } else {
+            break;
+        }
+        end += 1;
+    }
+
+    if let Some(last) = body.last_mut() {
+        last.terminated = false;
+    }
+    (vec![Element::BlockQuote(parse_lines(body))], end)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ListMarker {
+    /// number of an ordered list item
+    start: Option<usize>,
+    marker: char,
+    /// width of the marker and the spaces after it
+    width: usize,
+    /// the item has no content on its first line
+    empty: bool,
+}
+
+impl ListMarker {
+    fn continues(&self, other: &ListMarker) -> bool {
+        self.marker == other.marker && self.start.is_some() == other.start.is_some()
+    }
+}
+
+fn list_marker(line: &Line) -> Option<ListMarker> {
+    let head = line.head();
+    let indent = line.indent();
+    if indent >= CODE_INDENT || line.is_text() && is_thematic_break(head) {
+        return None;
+    }
+
+    let rest: Vec<char> = head.chars().skip(indent).collect();
+    let (start, marker, marker_len) = match rest.first()? {
+        '-' | '*' | '+' => (None, rest[0], 1),
+        _ => {
+            let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+            let delimiter = *rest.get(digits)?;
+            if digits == 0 || digits > LIST_NUMBER_DIGITS_MAX || !matches!(delimiter, '.' | ')') {
+                return None;
+            }
+            let number = rest[..digits].iter().collect::<String>().parse().ok()?;
+            (Some(number), delimiter, digits + 1)
+        }
+    };
+
+    let spaces = rest[marker_len..].iter().take_while(|c| **c == ' ').count();
+    let empty = marker_len + spaces == rest.len() && line.items.len() <= line.content_start() + 1;
+    if spaces == 0 && !empty {
+        return None;
+    }
+    let spaces = if empty || spaces > CODE_INDENT {
+        1
+    } else {
+        spaces
+    };
+
+    Some(ListMarker {
+        start,
+        marker,
+        width: indent + marker_len + spaces,
+        empty,
+    })
+}
+
+fn parse_list(lines: &[Line], i: usize, first: ListMarker) -> (Vec<Element>, usize) {
+    let mut items = Vec::new();
+    let mut start = i;
+    let mut marker = first;
+    loop {
+        let mut body = vec![lines[start].clone().strip(marker.width)];
+        let mut end = start + 1;
+        while end < lines.len() {
+            let line = &lines[end];
+            if line.is_blank() {
+                body.push(line.clone());
+            } else if line.indent() >= marker.width {
+                body.push(line.clone().strip(marker.width));
+            } else if !lines[end - 1].is_blank()
+                && !interrupts_paragraph(line)
+                && list_marker(line).is_none()
+                && body.iter().any(|line| !line.is_blank())
+            {
+                // lazy continuation of a paragraph
+                body.push(line.clone());
+            } else {
+                break;
+            }
+            end += 1;
+        }
+
+        let next = lines.get(end).and_then(list_marker);
+        match next {
+            Some(next) if next.continues(&marker) => {
+                items.push(parse_lines(body));
+                start = end;
+                marker = next;
+            }
+            _ => {
+                // trailing blank lines belong to the enclosing block
+                while body.len() > 1 && body.last().is_some_and(Line::is_blank) {
+                    body.pop();
+                    end -= 1;
+                }
+                if let Some(last) = body.last_mut() {
+                    last.terminated = false;
+                }
+                items.push(parse_lines(body));
+                let list = Element::List {
+                    start: first.start,
+                    marker: first.marker,
+                    items,
+                };
+                return (vec![list], end);
+            }
+        }
+    }
+}
+
+fn parse_table(lines: &[Line], i: usize) -> Option<(Vec<Element>, usize)> {
+    let line = &lines[i];
+    let delimiter = lines.get(i + 1)?;
+    if line.indent() >= CODE_INDENT || !line.text().contains('|') || !delimiter.is_text() {
+        return None;
+    }
+
+    let alignments = split_cells(delimiter)
+        .into_iter()
+        .map(|cell| {
+            let cell = match cell.content.as_slice() {
+                [Element::Text(text)] => text.as_str(),
+                _ => "",
+            };
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut header: Vec<Vec<Element>> = split_cells(line)
+        .into_iter()
+        .map(Cell::into_elements)
+        .collect();
+    if header.len() != alignments.len() {
+        return None;
+    }
+    if let Some(last) = header.last_mut() {
+        last.push(Element::Trivia(format!("\n{}", delimiter.source())));
+    }
+
+    let mut rows = Vec::new();
+    let mut end = i + 2;
+    while end < lines.len()
+        && !lines[end].is_blank()
+        && !interrupts_paragraph(&lines[end])
+        && !lines[end].is_standalone()
+    {
+        let mut cells = split_cells(&lines[end]);
+        // GFM ignores the cells beyond the header, so they stay as written
+        let excess: String = cells
+            .split_off(alignments.len().min(cells.len()))
+            .iter()
+            .map(Cell::source)
+            .collect();
+        let mut row: Vec<Vec<Element>> = cells.into_iter().map(Cell::into_elements).collect();
+        if !excess.is_empty() {
+            row.last_mut().unwrap().push(Element::Trivia(excess));
+        }
+        if row.len() < alignments.len() {
+            row.resize_with(alignments.len(), Vec::new);
+        }
+        row[0].insert(0, Element::Trivia("\n".to_string()));
+        rows.push(row);
+        end += 1;
+    }
+
+    let table = Element::Table {
+        alignments,
+        header,
+        rows,
+    };
+    Some((vec![table], end))
+}
+
+/// A table cell with the pipes and spaces around its content.
+struct Cell {
+    before: String,
+    content: Vec<Element>,
+    after: String,
+}
+
+impl Cell {
+    /// the cell as written, without its zeta elements
+    fn source(&self) -> String {
+        let content: String = self
+            .content
+            .iter()
+            .filter_map(|item| match item {
+                Element::Text(text) | Element::Trivia(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        format!("{}{}{}", self.before, content, self.after)
+    }
+
+    fn into_elements(self) -> Vec<Element> {
+        let mut elements = Vec::new();
+        if !self.before.is_empty() {
+            elements.push(Element::Trivia(self.before));
+        }
+        elements.extend(inlines(self.content));
+        if !self.after.is_empty() {
+            elements.push(Element::Trivia(self.after));
+        }
+        elements
+    }
+}
+
+/// Splits a table row at the pipes outside code spans.
+fn split_cells(line: &Line) -> Vec<Cell> {
+    let mut cells = vec![Vec::new()];
+    let mut in_code = false;
+    for item in &line.items[line.content_start()..] {
+        let Element::Text(text) = item else {
+            cells.last_mut().unwrap().push(item.clone());
+            continue;
+        };
+        let mut cell = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    cell.push(c);
+                    if let Some(next) = chars.next() {
+                        cell.push(next);
+                    }
+                }
+                '`' => {
+                    in_code = !in_code;
+                    cell.push(c);
+                }
+                '|' if !in_code => {
+                    cells
+                        .last_mut()
+                        .unwrap()
+                        .push(Element::Text(std::mem::take(&mut cell)));
+                    cells.push(Vec::new());
+                }
+                _ => cell.push(c),
+            }
+        }
+        cells.last_mut().unwrap().push(Element::Text(cell));
+    }
+
+    let mut cells: Vec<Cell> = cells
+        .into_iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let mut cell = trim_cell(cell);
+            if i > 0 {
+                cell.before.insert(0, '|');
+            }
+            cell
+        })
+        .collect();
+    if line.text().trim_start().starts_with('|') && cells.len() > 1 && cells[0].content.is_empty() {
+        let first = cells.remove(0);
+        cells[0].before = first.before + &first.after + &cells[0].before;
+    }
+    let last = cells.len() - 1;
+    if line.text().trim_end().ends_with('|') && last > 0 && cells[last].content.is_empty() {
+        let last = cells.pop().unwrap();
+        cells.last_mut().unwrap().after += &(last.before + &last.after);
+    }
+    let markers: String = line
+        .trivia()
+        .iter()
+        .map(|item| match item {
+            Element::Trivia(text) => text.as_str(),
+            _ => "",
+        })
+        .collect();
+    cells[0].before.insert_str(0, &markers);
+    cells
+}
+
+fn trim_cell(cell: Vec<Element>) -> Cell {
+    let mut content = merge_text(cell);
+    let mut before = String::new();
+    let mut after = String::new();
+    if let Some(Element::Text(text)) = content.first_mut() {
+        let trimmed = text.trim_start();
+        before = text[..text.len() - trimmed.len()].to_string();
+        *text = trimmed.to_string();
+    }
+    if let Some(Element::Text(text)) = content.last_mut() {
+        let trimmed = text.trim_end();
+        after = text[trimmed.len()..].to_string();
+        *text = trimmed.to_string();
+    }
+    content.retain(|item| !matches!(item, Element::Text(text) if text.is_empty()));
+    Cell {
+        before,
+        content,
+        after,
+    }
+}
+
+fn is_html_start(line: &Line) -> bool {
+    let Some(rest) = line.head().trim_start().strip_prefix('<') else {
+        return false;
+    };
+    if line.indent() >= CODE_INDENT {
+        return false;
+    }
+    if rest.starts_with(['!', '?']) {
+        return true;
+    }
+
+    // a tag name, unlike the scheme of an autolink or an email address
+    let name = rest.strip_prefix('/').unwrap_or(rest);
+    let len = name
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .count();
+    let is_tag = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name[len..]
+            .chars()
+            .next()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '>' | '/'));
+    if !is_tag {
+        return false;
+    }
+    if HTML_BLOCK_TAGS.contains(&name[..len].to_ascii_lowercase().as_str()) {
+        return true;
+    }
+    // any other tag only when it stands alone on the line
+    rest.find('>')
+        .is_some_and(|close| rest[close + 1..].trim().is_empty() && line.is_text())
+}
+
+/// Structures the bodies of zeta elements.
+fn structure(element: Element, block: bool) -> Element {
+    match element {
+        Element::Message {
+            level,
+            msg_type,
+            body,
+        } => Element::Message {
+            level,
+            msg_type,
+            body: blocks(body),
+        },
+        Element::Details { level, title, body } => Element::Details {
+            level,
+            title,
+            body: blocks(body),
+        },
+        Element::Conditional { platforms, body } => Element::Conditional {
+            platforms,
+            body: if block { blocks(body) } else { inlines(body) },
+        },
+        Element::Heading { level, text } => Element::Heading {
+            level,
+            text: inlines(text),
+        },
+        Element::FootnoteDefinition { name, body } => Element::FootnoteDefinition {
+            name,
+            body: inlines(body),
+        },
+        Element::ArticleLink {
+            text,
+            slug,
+            qiita_id,
+        } => Element::ArticleLink {
+            text: inlines(text),
+            slug,
+            qiita_id,
+        },
+        Element::InlineFootnote(body) => Element::InlineFootnote(inlines(body)),
+        element => element,
+    }
+}
+
+enum Piece {
+    Text(String),
+    Element(Element),
+    Delimiter {
+        char: char,
+        count: usize,
+        can_open: bool,
+        can_close: bool,
+    },
+}
+
+/// Parses code spans, links and emphasis in the text of an inline sequence.
+pub fn inlines(elements: Vec<Element>) -> Vec<Element> {
+    let elements = merge_text(elements);
+    let mut pieces = Vec::new();
+    let mut previous = ' ';
+    for (i, element) in elements.iter().enumerate() {
+        match element {
+            Element::Text(text) => {
+                let next = match elements.get(i + 1) {
+                    Some(Element::Text(text)) => text.chars().next().unwrap_or(' '),
+                    Some(Element::Trivia(_)) | None => ' ',
+                    // zeta elements are treated like a word
+                    Some(_) => 'a',
+                };
+                scan_text(text, previous, next, &mut pieces);
+                previous = text.chars().next_back().unwrap_or(previous);
+            }
+            // markers of the enclosing blocks stand at the start of a line
+            Element::Trivia(_) => {
+                pieces.push(Piece::Element(element.clone()));
+                previous = ' ';
+            }
+            element => {
+                pieces.push(Piece::Element(structure(element.clone(), false)));
+                previous = 'a';
+            }
+        }
+    }
+    resolve_emphasis(pieces)
+}
+
+fn scan_text(text: &str, previous: char, next: char, pieces: &mut Vec<Piece>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut buffer = String::new();
+    let mut p = 0;
+    while p < chars.len() {
+        let c = chars[p];
+        match c {
+            '\\' if chars.get(p + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                buffer.extend(&chars[p..p + 2]);
+                p += 2;
+            }
+            '`' => {
+                let len = run_length(&chars, p);
+                match find_run(&chars, p + len, '`', len) {
+                    Some(close) => {
+                        flush(&mut buffer, pieces);
+                        let code = chars[p + len..close].iter().collect();
+                        pieces.push(Piece::Element(Element::InlineCode { code, fence: len }));
+                        p = close + len;
+                    }
+                    None => {
+                        buffer.extend(&chars[p..p + len]);
+                        p += len;
+                    }
+                }
+            }
+            // math is left to the platforms
+            '$' => {
+                let len = run_length(&chars, p).min(2);
+                let end = find_run(&chars, p + len, '$', len).map_or(p + len, |close| close + len);
+                buffer.extend(&chars[p..end]);
+                p = end;
+            }
+            // raw HTML and autolinks
+            '<' if chars
+                .get(p + 1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!')) =>
+            {
+                let end = chars[p..]
+                    .iter()
+                    .position(|c| *c == '>')
+                    .map_or(p + 1, |close| p + close + 1);
+                buffer.extend(&chars[p..end]);
+                p = end;
+            }
+            '[' => match parse_link(&chars, p) {
+                Some((link, end)) => {
+                    flush(&mut buffer, pieces);
+                    pieces.push(Piece::Element(link));
+                    p = end;
+                }
+                None => {
+                    buffer.push(c);
+                    p += 1;
+                }
+            },
+            '*' | '_' | '~' => {
+                let count = run_length(&chars, p);
+                let before = if p == 0 { previous } else { chars[p - 1] };
+                let after = chars.get(p + count).copied().unwrap_or(next);
+                if c == '~' && count != 2 {
+                    buffer.extend(&chars[p..p + count]);
+                } else {
+                    flush(&mut buffer, pieces);
+                    let (can_open, can_close) = flanking(c, before, after);
+                    pieces.push(Piece::Delimiter {
+                        char: c,
+                        count,
+                        can_open,
+                        can_close,
+                    });
+                }
+                p += count;
+            }
+            _ => {
+                buffer.push(c);
+                p += 1;
+            }
+        }
+    }
+    flush(&mut buffer, pieces);
+}
+
+fn flush(buffer: &mut String, pieces: &mut Vec<Piece>) {
+    if !buffer.is_empty() {
+        pieces.push(Piece::Text(std::mem::take(buffer)));
+    }
+}
+
+fn run_length(chars: &[char], p: usize) -> usize {
+    chars[p..].iter().take_while(|c| **c == chars[p]).count()
+}
+
+/// Finds a run of exactly `len` characters `c` from `p`.
+fn find_run(chars: &[char], mut p: usize, c: char, len: usize) -> Option<usize> {
+    while p < chars.len() {
+        if chars[p] == c {
+            let run = run_length(chars, p);
+            if run == len {
+                return Some(p);
+            }
+            p += run;
+        } else {
+            p += 1;
+        }
+    }
+    None
+}
+
+/// Whether a delimiter run can open and close emphasis.
+fn flanking(c: char, before: char, after: char) -> (bool, bool) {
+    let is_punctuation =
+        |c: char| c.is_ascii_punctuation() || !c.is_alphanumeric() && !c.is_whitespace();
+    let left = !after.is_whitespace()
+        && (!is_punctuation(after) || before.is_whitespace() || is_punctuation(before));
+    let right = !before.is_whitespace()
+        && (!is_punctuation(before) || after.is_whitespace() || is_punctuation(after));
+    if c == '_' {
+        (
+            left && (!right || is_punctuation(before)),
+            right && (!left || is_punctuation(after)),
+        )
+    } else {
+        (left, right)
+    }
+}
+
+/// `[text](url "title")`
+fn parse_link(chars: &[char], p: usize) -> Option<(Element, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    let mut q = p + 1;
+    while q < chars.len() {
+        match chars[q] {
+            '\\' => q += 1,
+            '[' => depth += 1,
+            ']' if depth == 0 => {
+                close = Some(q);
+                break;
+            }
+            ']' => depth -= 1,
+            _ => (),
+        }
+        q += 1;
+    }
+    let close = close?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+
+    let mut q = close + 2;
+    while chars.get(q) == Some(&' ') {
+        q += 1;
+    }
+    let url_start = q;
+    let url: String = if chars.get(q) == Some(&'<') {
+        let end = q + chars[q..].iter().position(|c| *c == '>')?;
+        q = end + 1;
+        chars[url_start..=end].iter().collect()
+    } else {
+        let mut depth = 0;
+        while q < chars.len() && !chars[q].is_whitespace() {
+            match chars[q] {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                _ => (),
+            }
+            q += 1;
+        }
+        chars[url_start..q].iter().collect()
+    };
+    while chars.get(q) == Some(&' ') {
+        q += 1;
+    }
+
+    let title = match chars.get(q) {
+        Some(&open @ ('"' | '\'' | '(')) => {
+            let end_char = if open == '(' { ')' } else { open };
+            let end = q + 1 + chars[q + 1..].iter().position(|c| *c == end_char)?;
+            let title = chars[q + 1..end].iter().collect();
+            q = end + 1;
+            while chars.get(q) == Some(&' ') {
+                q += 1;
+            }
+            Some(title)
+        }
+        _ => None,
+    };
+    if chars.get(q) != Some(&')') {
+        return None;
+    }
+
+    let mut text = vec![Element::Trivia("[".to_string())];
+    text.extend(inlines(vec![Element::Text(
+        chars[p + 1..close].iter().collect(),
+    )]));
+    text.push(Element::Trivia(chars[close..=q].iter().collect()));
+    let link = Element::Link { text, url, title };
+    Some((link, q + 1))
+}
+
+fn resolve_emphasis(pieces: Vec<Piece>) -> Vec<Element> {
+    let mut stack: Vec<Piece> = Vec::new();
+    for piece in pieces {
+        let Piece::Delimiter {
+            char,
+            mut count,
+            can_open,
+            can_close,
+        } = piece
+        else {
+            stack.push(piece);
+            continue;
+        };
+
+        while can_close && count > 0 {
+            let Some(opener) = stack.iter().rposition(|piece| {
+                matches!(piece, Piece::Delimiter { char: c, can_open: true, count: n, .. }
+                    if *c == char && (char != '~' || *n == count))
+            }) else {
+                break;
+            };
+            let Piece::Delimiter {
+                count: opener_count,
+                ..
+            } = &mut stack[opener]
+            else {
+                unreachable!();
+            };
+            let used = if char == '~' || count >= 2 && *opener_count >= 2 {
+                2
+            } else {
+                1
+            };
+            *opener_count -= used;
+            let remaining = *opener_count;
+
+            let delimiter = Element::Trivia(char.to_string().repeat(used));
+            let mut children = vec![delimiter.clone()];
+            children.extend(finish(stack.split_off(opener + 1)));
+            children.push(delimiter);
+            if remaining == 0 {
+                stack.pop();
+            }
+            stack.push(Piece::Element(match (char, used) {
+                ('~', _) => Element::Strikethrough(children),
+                (_, 2) => Element::Strong(children),
+                _ => Element::Emphasis(children),
+            }));
+            count -= used;
+        }
+
+        if count > 0 {
+            stack.push(Piece::Delimiter {
+                char,
+                count,
+                can_open,
+                can_close,
+            });
+        }
+    }
+    finish(stack)
+}
+
+fn finish(pieces: Vec<Piece>) -> Vec<Element> {
+    merge_text(
+        pieces
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => Element::Text(text),
+                Piece::Element(element) => element,
+                Piece::Delimiter { char, count, .. } => {
+                    Element::Text(char.to_string().repeat(count))
+                }
+            })
+            .collect(),
+    )
+}
+
+fn merge_text(elements: Vec<Element>) -> Vec<Element> {
+    let mut result: Vec<Element> = Vec::new();
+    for element in elements {
+        match (result.last_mut(), element) {
+            (_, Element::Text(text)) if text.is_empty() => (),
+            (Some(Element::Text(last)), Element::Text(text)) => last.push_str(&text),
+            (_, element) => result.push(element),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Element> {
+        blocks(vec![Element::Text(source.to_string())])
+    }
+
+    fn parse_inlines(source: &str) -> Vec<Element> {
+        inlines(vec![Element::Text(source.to_string())])
+    }
+
+    /// the text of the elements, without their markers
+    fn text(elements: &[Element]) -> String {
+        elements
+            .iter()
+            .map(|element| match element {
+                Element::Text(text) => text.clone(),
+                Element::InlineCode { code, .. } => code.clone(),
+                element => element
+                    .markdown_children()
+                    .into_iter()
+                    .map(|children| text(children))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// the elements as written
+    fn source(elements: &[Element]) -> String {
+        elements
+            .iter()
+            .map(|element| match element {
+                Element::Text(text) | Element::Trivia(text) => text.clone(),
+                Element::CodeBlock(source) | Element::ThematicBreak(source) => source.clone(),
+                Element::InlineCode { code, fence } => {
+                    let fence = "`".repeat(*fence);
+                    format!("{}{}{}", fence, code, fence)
+                }
+                element => element
+                    .markdown_children()
+                    .into_iter()
+                    .map(|children| source(children))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists() {
+        let markdown = "- a\n- b\n\n  c\n\n1. d\n2) e\n";
+        let elements = parse(markdown);
+        assert_eq!(source(&elements), markdown);
+
+        let [Element::List {
+            start: None,
+            marker: '-',
+            items,
+        }, _, Element::List {
+            start: Some(1),
+            marker: '.',
+            items: ordered,
+        }, _, Element::List {
+            start: Some(2),
+            marker: ')',
+            ..
+        }, _] = elements.as_slice()
+        else {
+            panic!("{:?}", elements);
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(text(&items[1]), "b\n\nc");
+        assert_eq!(ordered.len(), 1);
+    }
+
+    #[test]
+    fn block_quotes() {
+        let markdown = "> a\nb\n> > c\n\nd";
+        let elements = parse(markdown);
+        assert_eq!(source(&elements), markdown);
+
+        let [Element::BlockQuote(quote), _, Element::Paragraph(after)] = elements.as_slice() else {
+            panic!("{:?}", elements);
+        };
+        assert!(matches!(
+            quote.as_slice(),
+            [Element::Paragraph(_), _, Element::BlockQuote(_)]
+        ));
+        // the lazy continuation belongs to the quoted paragraph
+        assert_eq!(text(&quote[..1]), "a\nb");
+        assert_eq!(text(after), "d");
+    }
+
+    #[test]
+    fn tables() {
+        let markdown = "| a | b |\n|:-|-:|\n| 1 | 2 | 3 |\n| 4 |\n";
+        let elements = parse(markdown);
+        assert_eq!(source(&elements), markdown);
+
+        let [Element::Table {
+            alignments,
+            header,
+            rows,
+        }, _] = elements.as_slice()
+        else {
+            panic!("{:?}", elements);
+        };
+        assert_eq!(alignments, &[Alignment::Left, Alignment::Right]);
+        assert_eq!(header.len(), 2);
+        // the cells beyond the header are dropped, the missing ones are empty
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| row.iter().map(|cell| text(cell)).collect())
+            .collect();
+        assert_eq!(cells, [vec!["1", "2"], vec!["4", ""]]);
+    }
+
+    #[test]
+    fn emphasis_flanking() {
+        let elements = parse_inlines("foo*bar* a * b * _a_b snake_case_name **x** __y__z");
+        let emphasized: Vec<String> = elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Emphasis(children) => Some(format!("em {}", text(children))),
+                Element::Strong(children) => Some(format!("strong {}", text(children))),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(emphasized, ["em bar", "strong x"]);
+        assert_eq!(
+            text(&elements),
+            "foobar a * b * _a_b snake_case_name x __y__z"
+        );
+    }
+
+    #[test]
+    fn code_spans() {
+        let elements = parse_inlines("`a|b` ``a`b`` `*x*");
+        let code: Vec<(&str, usize)> = elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::InlineCode { code, fence } => Some((code.as_str(), *fence)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(code, [("a|b", 1), ("a`b", 2)]);
+        // an unclosed backtick is text and does not hide the emphasis after it
+        assert!(matches!(elements.last(), Some(Element::Emphasis(_))));
+
+        let elements = parse("| `a|b` | c |\n|-|-|\n");
+        let [Element::Table { header, .. }, _] = elements.as_slice() else {
+            panic!("{:?}", elements);
+        };
+        assert_eq!(header.len(), 2);
+    }
+
+    #[test]
+    fn autolinks() {
+        let markdown = "<https://example.com/a_b_c> and <me@example.com>";
+        let elements = parse(markdown);
+        let [Element::Paragraph(children)] = elements.as_slice() else {
+            panic!("{:?}", elements);
+        };
+        assert!(matches!(children.as_slice(), [Element::Text(text)] if text == markdown));
+    }
+
+    #[test]
+    fn html() {
+        let markdown = "<div>\n*x*\n</div>\n\n<span>\n*y*\n\n<span>*z*</span>";
+        let elements = parse(markdown);
+        assert_eq!(source(&elements), markdown);
+
+        let [Element::Html(div), _, Element::Html(span), _, Element::Paragraph(inline)] =
+            elements.as_slice()
+        else {
+            panic!("{:?}", elements);
+        };
+        assert!(matches!(div.as_slice(), [Element::Text(_)]));
+        assert!(matches!(span.as_slice(), [Element::Text(_)]));
+        // a tag followed by text starts a paragraph, unless it is a block tag
+        assert!(matches!(inline.as_slice(), [_, Element::Emphasis(_), _]));
+
+        let elements = parse_inlines("a <span>*y*</span> <b c=\"*\">");
+        assert!(matches!(
+            elements.as_slice(),
+            [Element::Text(_), Element::Emphasis(_), Element::Text(text)] if text == "</span> <b c=\"*\">"
+        ));
+    }
+}
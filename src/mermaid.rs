@@ -0,0 +1,97 @@
+use std::{fs, process::Command};
+
+use crate::{
+    ast::{Element, ParsedMd},
+    print::{zeta_error, zeta_message},
+};
+
+/// Renders every mermaid block to `images/<target>/mermaid-<n>.svg`
+/// and replaces it with an image referring to the rendered file.
+pub fn render_mermaid(mut file: ParsedMd, target: &str) -> ParsedMd {
+    let mut index = 0;
+    file.elements = render_elements(file.elements, target, &mut index);
+    file
+}
+
+fn render_elements(elements: Vec<Element>, target: &str, index: &mut usize) -> Vec<Element> {
+    elements
+        .into_iter()
+        .map(|element| render_element(element, target, index))
+        .collect()
+}
+
+fn render_element(element: Element, target: &str, index: &mut usize) -> Element {
+    match element {
+        Element::Mermaid { code, source } => {
+            *index += 1;
+            match render_svg(&code, target, *index) {
+                Some(url) => Element::Image {
+                    alt: format!("mermaid-{}", index),
+                    url,
+                    width: None,
+                    caption: None,
+                },
+                None => Element::Mermaid { code, source },
+            }
+        }
+        Element::Macro(mut macro_info) => {
+            macro_info.zenn = render_elements(macro_info.zenn, target, index);
+            macro_info.qiita = render_elements(macro_info.qiita, target, index);
+            Element::Macro(macro_info)
+        }
+        Element::Message {
+            level,
+            msg_type,
+            body,
+        } => Element::Message {
+            level,
+            msg_type,
+            body: render_elements(body, target, index),
+        },
+        Element::Details { level, title, body } => Element::Details {
+            level,
+            title,
+            body: render_elements(body, target, index),
+        },
+        Element::Conditional { platforms, body } => Element::Conditional {
+            platforms,
+            body: render_elements(body, target, index),
+        },
+        element => element.map_markdown_children(|children| render_elements(children, target, index)),
+    }
+}
+
+fn render_svg(code: &str, target: &str, index: usize) -> Option<String> {
+    let input = std::env::temp_dir().join(format!("zeta-{}-mermaid-{}.mmd", target, index));
+    let output = format!("images/{}/mermaid-{}.svg", target, index);
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .create(format!("images/{}", target))
+        .unwrap();
+    fs::write(&input, code).unwrap();
+
+    zeta_message(&format!("Rendering {}...", output));
+    let result = Command::new("npx")
+        .args(["-y", "-p", "@mermaid-js/mermaid-cli", "mmdc", "-i"])
+        .arg(&input)
+        .args(["-o", &output])
+        .output();
+    let _ = fs::remove_file(&input);
+
+    match result {
+        Ok(result) if result.status.success() => Some(format!("/{}", output)),
+        Ok(result) => {
+            zeta_error(&format!(
+                "Failed to render mermaid diagram #{}\n{}",
+                index,
+                String::from_utf8_lossy(&result.stderr)
+            ));
+            None
+        }
+        Err(error) => {
+            zeta_error(&format!("Failed to run mermaid-cli: {}", error));
+            None
+        }
+    }
+}
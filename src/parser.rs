@@ -1,15 +1,24 @@
-use std::fmt::Display;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    ast::{Element, MarkdownDoc, MessageType, ParsedMd, TokenizedMd, ZetaFrontmatter},
-    r#macro::ParsedMacro,
+    article,
+    ast::{Element, MarkdownDoc, MessageType, ParsedMd, TokenizedMd, Variable, ZetaFrontmatter},
+    markdown,
+    r#macro::{ParsedMacro, StringMacro},
+    scanner::Scanner,
     token::{Token, TokenType},
 };
 
-const FRONTMATTER_TOPICS_MAX: usize = 5;
-
 type Result<T> = std::result::Result<T, ParseError>;
 
+/// Directory of the snippets that articles can include.
+const SNIPPETS_DIR: &str = "snippets";
+
 #[derive(Debug, Clone)]
 pub struct ParseError {
     pub error_type: ParseErrorType,
@@ -30,9 +39,29 @@ impl ParseError {
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseErrorType {
-    TooManyTopics(Vec<String>),
     InvalidFrontMatter,
     InvalidMacro,
+    UndefinedMacro(String),
+    /// the macros from the first reference back to itself
+    RecursiveMacro(Vec<String>),
+    InvalidMacroDefinition(String),
+    InMacro { name: String, message: String },
+    InvalidFootnoteDefinition,
+    InvalidInlineFootnote,
+    InvalidConditional,
+    UndefinedFootnote(String),
+    UnusedFootnoteDefinition(String),
+    DuplicateFootnoteDefinition(String),
+    InvalidInclude(String),
+    IncludeNotFound(String),
+    IncludeOutsideSnippets(String),
+    IncludeCycle(String),
+    InInclude { path: String, message: String },
+    ArticleNotFound(String),
+    InvalidArticleLink(String),
+    InvalidHeading,
+    TitleHeading,
+    SkippedHeadingLevel { previous: usize, level: usize },
     InvalidMessageType,
     InvalidNestingLevel(usize),
     CouldNotFindEndToken(TokenType),
@@ -51,14 +80,62 @@ impl Display for ParseError {
 impl Display for ParseErrorType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseErrorType::TooManyTopics(topics) => write!(
-                f,
-                "Too many topics: [{}]. The maximum number of topics is {}.",
-                topics.join(", "),
-                FRONTMATTER_TOPICS_MAX
-            ),
             ParseErrorType::InvalidFrontMatter => write!(f, "Invalid front matter"),
             ParseErrorType::InvalidMacro => write!(f, "Invalid macro"),
+            ParseErrorType::UndefinedMacro(name) => {
+                write!(f, "Undefined macro or variable '{}'.", name)
+            }
+            ParseErrorType::RecursiveMacro(names) => write!(
+                f,
+                "Macro '{}' refers to itself: {}.",
+                names[0],
+                names.join(" -> ")
+            ),
+            ParseErrorType::InvalidMacroDefinition(name) => {
+                write!(f, "Invalid definition of macro '{}'.", name)
+            }
+            ParseErrorType::InMacro { name, message } => write!(f, "Macro '{}': {}", name, message),
+            ParseErrorType::InvalidFootnoteDefinition => write!(f, "Invalid footnote definition"),
+            ParseErrorType::InvalidInlineFootnote => write!(f, "Invalid inline footnote"),
+            ParseErrorType::InvalidConditional => write!(f, "Invalid platform-conditional block"),
+            ParseErrorType::UndefinedFootnote(name) => {
+                write!(f, "Footnote '{}' is referenced but not defined.", name)
+            }
+            ParseErrorType::UnusedFootnoteDefinition(name) => {
+                write!(f, "Footnote '{}' is defined but never referenced.", name)
+            }
+            ParseErrorType::DuplicateFootnoteDefinition(name) => {
+                write!(f, "Footnote '{}' is defined more than once.", name)
+            }
+            ParseErrorType::InvalidInclude(path) => write!(f, "Invalid include '{}'.", path),
+            ParseErrorType::IncludeNotFound(path) => {
+                write!(f, "Included file '{}' is not found.", path)
+            }
+            ParseErrorType::IncludeOutsideSnippets(path) => write!(
+                f,
+                "Included file '{}' is outside the '{}' directory.",
+                path, SNIPPETS_DIR
+            ),
+            ParseErrorType::IncludeCycle(path) => {
+                write!(f, "'{}' is included recursively.", path)
+            }
+            ParseErrorType::InInclude { path, message } => write!(f, "{}: {}", path, message),
+            ParseErrorType::ArticleNotFound(slug) => {
+                write!(f, "Linked article '{}' is not found.", slug)
+            }
+            ParseErrorType::InvalidArticleLink(slug) => {
+                write!(f, "Invalid link to article '{}'.", slug)
+            }
+            ParseErrorType::InvalidHeading => write!(f, "Invalid heading"),
+            ParseErrorType::TitleHeading => write!(
+                f,
+                "The article starts with a level-1 heading. Zenn and Qiita already show the title."
+            ),
+            ParseErrorType::SkippedHeadingLevel { previous, level } => write!(
+                f,
+                "Heading level skips from {} to {}.",
+                previous, level
+            ),
             ParseErrorType::InvalidMessageType => write!(f, "Invalid message type"),
             ParseErrorType::InvalidNestingLevel(level) => write!(
                 f,
@@ -84,6 +161,15 @@ pub struct Parser {
 
     nesting_levels: Vec<usize>,
 
+    /// named macros available through `{{name}}`
+    macros: BTreeMap<String, StringMacro>,
+    /// names of the macros being expanded
+    macro_stack: Vec<String>,
+    /// directory that included paths have to be in
+    snippets: PathBuf,
+    /// canonical paths of the snippets being included
+    include_stack: Vec<PathBuf>,
+
     errors: Vec<ParseError>,
 }
 
@@ -94,10 +180,30 @@ impl Parser {
             frontmatter: md.frontmatter,
             position: 0,
             nesting_levels: Vec::new(),
+            macros: BTreeMap::new(),
+            macro_stack: Vec::new(),
+            snippets: PathBuf::from(SNIPPETS_DIR),
+            include_stack: Vec::new(),
             errors: Vec::new(),
         }
     }
 
+    /// Sets project-wide macros. Macros in the frontmatter take precedence.
+    pub fn with_macros(mut self, macros: BTreeMap<String, StringMacro>) -> Self {
+        self.macros = macros;
+        self
+    }
+
+    /// Creates a parser for tokens nested in the current document.
+    fn sub_parser(&self, tokens: Vec<Token>) -> Parser {
+        let mut parser = Parser::new(MarkdownDoc::new(String::new(), tokens))
+            .with_macros(self.macros.clone());
+        parser.macro_stack = self.macro_stack.clone();
+        parser.snippets = self.snippets.clone();
+        parser.include_stack = self.include_stack.clone();
+        parser
+    }
+
     pub fn parse(mut self) -> std::result::Result<ParsedMd, Vec<ParseError>> {
         let frontmatter = match self.parse_frontmatter() {
             Ok(frontmatter) => frontmatter,
@@ -107,7 +213,21 @@ impl Parser {
             }
         };
 
-        let elements = self.parse_body()?;
+        self.parse_with(frontmatter)
+    }
+
+    /// Parses the body with a frontmatter given by the caller, e.g. a book chapter.
+    pub fn parse_with(
+        mut self,
+        frontmatter: ZetaFrontmatter,
+    ) -> std::result::Result<ParsedMd, Vec<ParseError>> {
+        self.macros.extend(frontmatter.macros.clone());
+        let mut tokens = Vec::new();
+        self.splice_includes(&self.source, None, &mut Vec::new(), &mut tokens);
+        self.check_footnotes(&tokens);
+        self.check_headings(&tokens);
+
+        let elements = markdown::blocks(self.parse_body()?);
 
         Ok(ParsedMd {
             elements,
@@ -118,7 +238,7 @@ impl Parser {
     fn parse_frontmatter(&mut self) -> Result<ZetaFrontmatter> {
         let content = &self.frontmatter;
 
-        let result = serde_yaml::from_str::<ZetaFrontmatter>(content).map_err(|error| {
+        serde_yaml::from_str::<ZetaFrontmatter>(content).map_err(|error| {
             let (row, col) = if let Some(location) = error.location() {
                 (location.line(), location.column())
             } else {
@@ -126,19 +246,116 @@ impl Parser {
             };
 
             ParseError::new(ParseErrorType::InvalidFrontMatter, row, col)
-        });
+        })
+    }
 
-        if let Ok(frontmatter) = &result {
-            if frontmatter.topics.len() > FRONTMATTER_TOPICS_MAX {
-                return Err(ParseError::new(
-                    ParseErrorType::TooManyTopics(frontmatter.topics.clone()),
-                    0,
-                    0,
+    /// Collects the tokens of the document with the snippets it includes spliced in.
+    /// Includes that can't be read are skipped, as parse_include() reports them.
+    fn splice_includes(
+        &self,
+        tokens: &[Token],
+        path: Option<&str>,
+        include_stack: &mut Vec<PathBuf>,
+        spliced: &mut Vec<SplicedToken>,
+    ) {
+        for token in tokens {
+            let TokenType::Include(include) = &token.token_type else {
+                spliced.push((token.clone(), path.map(str::to_string)));
+                continue;
+            };
+            let Ok(resolved) = self.resolve_include(include) else {
+                continue;
+            };
+            if include_stack.contains(&resolved) {
+                continue;
+            }
+            let Ok(source) = fs::read_to_string(&resolved) else {
+                continue;
+            };
+            let Ok(included) = Scanner::new(source.chars().collect()).scan_snippet() else {
+                continue;
+            };
+            include_stack.push(resolved);
+            self.splice_includes(&included, Some(include), include_stack, spliced);
+            include_stack.pop();
+        }
+    }
+
+    /// Verifies that every footnote reference has a definition and vice versa.
+    fn check_footnotes(&mut self, tokens: &[SplicedToken]) {
+        let mut references = Vec::new();
+        let mut definitions = Vec::new();
+        for (token, path) in tokens {
+            collect_footnotes(
+                std::slice::from_ref(token),
+                path,
+                &mut references,
+                &mut definitions,
+            );
+        }
+
+        for (name, row, col, path) in &references {
+            if !definitions.iter().any(|(definition, _, _, _)| definition == name) {
+                self.errors.push(spliced_error(
+                    ParseErrorType::UndefinedFootnote(name.clone()),
+                    *row,
+                    *col,
+                    path,
                 ));
             }
         }
 
-        result
+        for (i, (name, row, col, path)) in definitions.iter().enumerate() {
+            if definitions[..i].iter().any(|(definition, _, _, _)| definition == name) {
+                self.errors.push(spliced_error(
+                    ParseErrorType::DuplicateFootnoteDefinition(name.clone()),
+                    *row,
+                    *col,
+                    path,
+                ));
+            } else if !references.iter().any(|(reference, _, _, _)| reference == name) {
+                self.errors.push(spliced_error(
+                    ParseErrorType::UnusedFootnoteDefinition(name.clone()),
+                    *row,
+                    *col,
+                    path,
+                ));
+            }
+        }
+    }
+
+    /// The title is shown as the level-1 heading, so the body starts below it.
+    fn check_headings(&mut self, tokens: &[SplicedToken]) {
+        let mut previous = 1;
+        let mut at_start = true;
+        for (token, path) in tokens {
+            match &token.token_type {
+                TokenType::Text(text) if text.trim().is_empty() => continue,
+                TokenType::Heading { level, .. } => {
+                    if at_start && *level == 1 {
+                        self.errors.push(spliced_error(
+                            ParseErrorType::TitleHeading,
+                            token.row,
+                            token.col,
+                            path,
+                        ));
+                    } else if *level > previous + 1 {
+                        self.errors.push(spliced_error(
+                            ParseErrorType::SkippedHeadingLevel {
+                                previous,
+                                level: *level,
+                            },
+                            token.row,
+                            token.col,
+                            path,
+                        ));
+                    }
+                    previous = *level;
+                }
+                _ => (),
+            }
+            at_start = false;
+        }
     }
 
     fn parse_body(mut self) -> std::result::Result<Vec<Element>, Vec<ParseError>> {
@@ -149,16 +366,30 @@ impl Parser {
         Ok(elements)
     }
 
-    fn parse_block(&mut self, end: Option<TokenType>) -> Vec<Element> {
+    /// Parses up to the `end` of the block opened at `row` and `col`, or to the last token.
+    fn parse_block(&mut self, end: Option<(TokenType, usize, usize)>) -> Vec<Element> {
         let mut elements = Vec::new();
 
         while let Some(token) = self.peek() {
-            if let Some(ref end) = end {
+            if let Some((ref end, _, _)) = end {
                 if token.token_type == *end {
                     break;
                 }
             }
 
+            if let TokenType::Include(path) = &token.token_type {
+                let (path, row, col) = (path.clone(), token.row, token.col);
+                self.advance();
+                match self.parse_include(path, row, col) {
+                    Ok(included) => elements.extend(included),
+                    Err(error) => {
+                        self.errors.push(error);
+                        break;
+                    }
+                }
+                continue;
+            }
+
             let element = match self.parse_element() {
                 Ok(element) => element,
                 Err(error) => {
@@ -170,12 +401,12 @@ impl Parser {
             elements.push(element);
         }
 
-        if let Some(end) = end {
+        if let Some((end, row, col)) = end {
             if self.peek().is_none() {
                 self.errors.push(ParseError::new(
                     ParseErrorType::CouldNotFindEndToken(end),
-                        0,
-                        0
+                    row,
+                    col,
                 ));
             }
         }
@@ -183,6 +414,23 @@ impl Parser {
         elements
     }
 
+    /// Parses the body of a `:::message` or `:::details` with its opening and closing lines as trivia.
+    fn parse_container(
+        &mut self,
+        open: String,
+        level: usize,
+        row: usize,
+        col: usize,
+    ) -> Vec<Element> {
+        let end = TokenType::MessageOrDetailsEnd { level };
+        let mut body = vec![Element::Trivia(open)];
+        body.extend(self.parse_block(Some((end.clone(), row, col))));
+        if let Some(close) = self.advance().filter(|token| token.token_type == end) {
+            body.push(Element::Trivia(close.source.clone()));
+        }
+        body
+    }
+
     fn parse_element(&mut self) -> Result<Element> {
         let Some(token) = self.advance().cloned() else {
             unreachable!("parse_element() should not be called when source is empty");
@@ -190,11 +438,101 @@ impl Parser {
 
         let elem = match token.token_type {
             TokenType::Text(text) => Element::Text(text),
+            TokenType::Trivia(text) => Element::Trivia(text),
             TokenType::Url(url) => Element::Url(url),
-            TokenType::Image { alt, url } => Element::Image { alt, url },
+            TokenType::Image {
+                alt,
+                url,
+                width,
+                caption,
+            } => Element::Image {
+                alt,
+                url,
+                width,
+                caption,
+            },
             TokenType::LinkCard { card_type, url } => Element::LinkCard { card_type, url },
-            TokenType::InlineFootnote(footnote) => Element::InlineFootnote(footnote),
+            TokenType::InlineFootnote(footnote) => {
+                let parser = self.sub_parser(footnote);
+                let footnote = match parser.parse_body() {
+                    Ok(footnote) => footnote,
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return Err(ParseError::new(
+                            ParseErrorType::InvalidInlineFootnote,
+                            token.row,
+                            token.col,
+                        ));
+                    }
+                };
+
+                Element::InlineFootnote(footnote)
+            }
             TokenType::Footnote(footnote) => Element::Footnote(footnote),
+            TokenType::ArticleLink { text, slug } => {
+                let Some(target) = article::read_zeta_frontmatter(&slug) else {
+                    return Err(ParseError::new(
+                        ParseErrorType::ArticleNotFound(slug),
+                        token.row,
+                        token.col,
+                    ));
+                };
+                let qiita_id = article::resolve_qiita_id(&slug, &target);
+
+                let text = match self.sub_parser(text).parse_body() {
+                    Ok(text) => text,
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return Err(ParseError::new(
+                            ParseErrorType::InvalidArticleLink(slug),
+                            token.row,
+                            token.col,
+                        ));
+                    }
+                };
+
+                Element::ArticleLink {
+                    text,
+                    slug,
+                    qiita_id,
+                }
+            }
+            TokenType::Mermaid(code) => Element::Mermaid {
+                code,
+                source: token.source,
+            },
+            TokenType::SlideBreak { title } => Element::SlideBreak { title },
+            TokenType::Heading { level, text } => {
+                let text = match self.sub_parser(text).parse_body() {
+                    Ok(text) => text,
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return Err(ParseError::new(
+                            ParseErrorType::InvalidHeading,
+                            token.row,
+                            token.col,
+                        ));
+                    }
+                };
+                Element::Heading { level, text }
+            }
+            TokenType::Toc => Element::Toc,
+            TokenType::FootnoteDefinition { name, body } => {
+                let parser = self.sub_parser(body);
+                let body = match parser.parse_body() {
+                    Ok(body) => body,
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return Err(ParseError::new(
+                            ParseErrorType::InvalidFootnoteDefinition,
+                            token.row,
+                            token.col,
+                        ));
+                    }
+                };
+
+                Element::FootnoteDefinition { name, body }
+            }
             TokenType::MessageBegin { level, r#type } => {
                 let msg_type = match r#type.as_str() {
                     "info" => MessageType::Info,
@@ -209,8 +547,7 @@ impl Parser {
                     }
                 };
                 self.nest(level, token.row, token.col)?;
-                let body = self.parse_block(Some(TokenType::MessageOrDetailsEnd { level }));
-                self.advance();
+                let body = self.parse_container(token.source, level, token.row, token.col);
                 self.unnest();
                 Element::Message {
                     level,
@@ -220,17 +557,51 @@ impl Parser {
             }
             TokenType::DetailsBegin { level, title } => {
                 self.nest(level, token.row, token.col)?;
-                let body = self.parse_block(Some(TokenType::MessageOrDetailsEnd { level }));
-                self.advance();
+                let body = self.parse_container(token.source, level, token.row, token.col);
                 self.unnest();
                 Element::Details { level, title, body }
             }
+            TokenType::ConditionalBegin { level, platforms } => {
+                self.nest(level, token.row, token.col)?;
+                let end = TokenType::MessageOrDetailsEnd { level };
+                let body = self.parse_block(Some((end, token.row, token.col)));
+                self.advance();
+                self.unnest();
+                Element::Conditional { platforms, body }
+            }
+            TokenType::Conditional { platforms, body } => {
+                let parser = self.sub_parser(body);
+                let body = match parser.parse_body() {
+                    Ok(body) => body,
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return Err(ParseError::new(
+                            ParseErrorType::InvalidConditional,
+                            token.row,
+                            token.col,
+                        ));
+                    }
+                };
+
+                Element::Conditional { platforms, body }
+            }
+            TokenType::MacroReference(name) => {
+                if self.macros.contains_key(&name) {
+                    Element::Macro(self.expand_macro(name, token.row, token.col)?)
+                } else if let Some(variable) = Variable::from_name(&name) {
+                    Element::Variable(variable)
+                } else {
+                    return Err(ParseError::new(
+                        ParseErrorType::UndefinedMacro(name),
+                        token.row,
+                        token.col,
+                    ));
+                }
+            }
+            TokenType::Include(_) => unreachable!("includes are spliced in parse_block()"),
             TokenType::MessageOrDetailsEnd { level: _ } => Element::Text("".to_string()),
             TokenType::Macro(macro_info) => {
-                let zenn_parser = Parser::new(MarkdownDoc {
-                    frontmatter: String::new(),
-                    elements: macro_info.zenn,
-                });
+                let zenn_parser = self.sub_parser(macro_info.zenn);
                 let zenn_elements = match zenn_parser.parse_body() {
                     Ok(zenn_elements) => zenn_elements,
                     Err(errors) => {
@@ -243,10 +614,7 @@ impl Parser {
                     }
                 };
 
-                let qiita_parser = Parser::new(MarkdownDoc {
-                    frontmatter: String::new(),
-                    elements: macro_info.qiita,
-                });
+                let qiita_parser = self.sub_parser(macro_info.qiita);
                 let qiita_elements = match qiita_parser.parse_body() {
                     Ok(qiita_elements) => qiita_elements,
                     Err(errors) => {
@@ -269,6 +637,165 @@ impl Parser {
         Ok(elem)
     }
 
+    /// Scans and parses a snippet to splice its elements into the document.
+    fn parse_include(&mut self, path: String, row: usize, col: usize) -> Result<Vec<Element>> {
+        let resolved = match self.resolve_include(&path) {
+            Ok(resolved) => resolved,
+            Err(error_type) => return Err(ParseError::new(error_type, row, col)),
+        };
+
+        if self.include_stack.contains(&resolved) {
+            return Err(ParseError::new(
+                ParseErrorType::IncludeCycle(path),
+                row,
+                col,
+            ));
+        }
+
+        let Ok(source) = fs::read_to_string(&resolved) else {
+            return Err(ParseError::new(
+                ParseErrorType::IncludeNotFound(path),
+                row,
+                col,
+            ));
+        };
+
+        let scanner = Scanner::new(source.chars().collect());
+        let tokens = match scanner.scan_snippet() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                self.errors.extend(errors.into_iter().map(|error| {
+                    ParseError::new(
+                        ParseErrorType::InInclude {
+                            path: path.clone(),
+                            message: error.error_type.to_string(),
+                        },
+                        error.row,
+                        error.col,
+                    )
+                }));
+                return Err(ParseError::new(
+                    ParseErrorType::InvalidInclude(path),
+                    row,
+                    col,
+                ));
+            }
+        };
+
+        let mut parser = self.sub_parser(tokens);
+        parser.include_stack.push(resolved);
+        match parser.parse_body() {
+            Ok(elements) => Ok(elements),
+            Err(errors) => {
+                self.errors.extend(errors.into_iter().map(|error| {
+                    ParseError::new(
+                        ParseErrorType::InInclude {
+                            path: path.clone(),
+                            message: error.error_type.to_string(),
+                        },
+                        error.row,
+                        error.col,
+                    )
+                }));
+                Err(ParseError::new(
+                    ParseErrorType::InvalidInclude(path),
+                    row,
+                    col,
+                ))
+            }
+        }
+    }
+
+    /// Resolves an included path against the project, which has to stay in the snippets.
+    fn resolve_include(&self, path: &str) -> std::result::Result<PathBuf, ParseErrorType> {
+        let root = self.snippets.parent().unwrap_or(Path::new(""));
+        let Ok(resolved) = fs::canonicalize(root.join(path)) else {
+            return Err(ParseErrorType::IncludeNotFound(path.to_string()));
+        };
+        if !fs::canonicalize(&self.snippets).is_ok_and(|snippets| resolved.starts_with(snippets)) {
+            return Err(ParseErrorType::IncludeOutsideSnippets(path.to_string()));
+        }
+        Ok(resolved)
+    }
+
+    fn expand_macro(&mut self, name: String, row: usize, col: usize) -> Result<ParsedMacro> {
+        let Some(definition) = self.macros.get(&name).cloned() else {
+            return Err(ParseError::new(
+                ParseErrorType::UndefinedMacro(name),
+                row,
+                col,
+            ));
+        };
+
+        if let Some(start) = self
+            .macro_stack
+            .iter()
+            .position(|expanding| *expanding == name)
+        {
+            let mut names = self.macro_stack[start..].to_vec();
+            names.push(name);
+            return Err(ParseError::new(
+                ParseErrorType::RecursiveMacro(names),
+                row,
+                col,
+            ));
+        }
+
+        self.macro_stack.push(name.clone());
+        let zenn = self.expand_macro_value(&name, definition.zenn, row, col);
+        let qiita = self.expand_macro_value(&name, definition.qiita, row, col);
+        self.macro_stack.pop();
+
+        Ok(ParsedMacro {
+            zenn: zenn?,
+            qiita: qiita?,
+        })
+    }
+
+    fn expand_macro_value(
+        &mut self,
+        name: &str,
+        value: Option<String>,
+        row: usize,
+        col: usize,
+    ) -> Result<Vec<Element>> {
+        let value = value.unwrap_or_default();
+        let invalid = || {
+            ParseError::new(
+                ParseErrorType::InvalidMacroDefinition(name.to_string()),
+                row,
+                col,
+            )
+        };
+
+        // positions in the value don't point into the article, so the reference is reported
+        let scanner = Scanner::new(value.chars().collect());
+        let tokens = match scanner.scan_body() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                self.errors.extend(errors.into_iter().map(|error| {
+                    ParseError::new(
+                        ParseErrorType::InMacro {
+                            name: name.to_string(),
+                            message: error.error_type.to_string(),
+                        },
+                        row,
+                        col,
+                    )
+                }));
+                return Err(invalid());
+            }
+        };
+
+        match self.sub_parser(tokens).parse_body() {
+            Ok(elements) => Ok(elements),
+            Err(errors) => {
+                self.errors.extend(errors);
+                Err(invalid())
+            }
+        }
+    }
+
     fn advance(&mut self) -> Option<&Token> {
         let result = self.source.get(self.position);
         self.position += 1;
@@ -301,3 +828,308 @@ impl Parser {
             .expect("unnest() should be called only when nesting_levels is not empty");
     }
 }
+
+/// A token with the path of the included snippet it comes from.
+type SplicedToken = (Token, Option<String>);
+
+/// Name and position of a footnote, and the snippet it is in.
+type FootnoteLocation = (String, usize, usize, Option<String>);
+
+/// Reports an error in the document, or in the snippet the position belongs to.
+fn spliced_error(
+    error_type: ParseErrorType,
+    row: usize,
+    col: usize,
+    path: &Option<String>,
+) -> ParseError {
+    match path {
+        Some(path) => ParseError::new(
+            ParseErrorType::InInclude {
+                path: path.clone(),
+                message: error_type.to_string(),
+            },
+            row,
+            col,
+        ),
+        None => ParseError::new(error_type, row, col),
+    }
+}
+
+fn collect_footnotes(
+    tokens: &[Token],
+    path: &Option<String>,
+    references: &mut Vec<FootnoteLocation>,
+    definitions: &mut Vec<FootnoteLocation>,
+) {
+    for token in tokens {
+        match &token.token_type {
+            TokenType::Footnote(name) => {
+                references.push((name.clone(), token.row, token.col, path.clone()))
+            }
+            TokenType::FootnoteDefinition { name, body } => {
+                definitions.push((name.clone(), token.row, token.col, path.clone()));
+                collect_footnotes(body, path, references, definitions);
+            }
+            TokenType::InlineFootnote(body)
+            | TokenType::Conditional { body, .. }
+            | TokenType::ArticleLink { text: body, .. }
+            | TokenType::Heading { text: body, .. } => {
+                collect_footnotes(body, path, references, definitions)
+            }
+            TokenType::Macro(macro_info) => {
+                collect_footnotes(&macro_info.zenn, path, references, definitions);
+                collect_footnotes(&macro_info.qiita, path, references, definitions);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(body: &str) -> Vec<ParseErrorType> {
+        let tokens = Scanner::new(body.chars().collect()).scan_snippet().unwrap();
+        let parser = Parser::new(MarkdownDoc::new(String::new(), tokens));
+        match parser.parse_with(ZetaFrontmatter::default()) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.error_type).collect(),
+        }
+    }
+
+    #[test]
+    fn headings_skip_levels() {
+        assert_eq!(errors("## a\n### b\n## c\n"), Vec::new());
+        assert_eq!(
+            errors("## a\n#### b\n"),
+            vec![ParseErrorType::SkippedHeadingLevel {
+                previous: 2,
+                level: 4
+            }]
+        );
+        assert_eq!(errors("# title\n"), vec![ParseErrorType::TitleHeading]);
+    }
+
+    #[test]
+    fn code_is_not_checked_for_headings() {
+        assert_eq!(
+            errors("~~~sh\n# comment\n#### comment\n~~~\n## a\n"),
+            Vec::new()
+        );
+        assert_eq!(
+            errors("    # comment\n\n## a\n\n    #### comment\n"),
+            Vec::new()
+        );
+        assert_eq!(errors("```sh\n# comment\n```\n## a\n"), Vec::new());
+    }
+
+    fn errors_with_macros(body: &str, macros: &[(&str, &str)]) -> Vec<ParseErrorType> {
+        let macros = macros
+            .iter()
+            .map(|(name, value)| {
+                let value = Some(value.to_string());
+                let r#macro = StringMacro {
+                    zenn: value.clone(),
+                    qiita: value,
+                };
+                (name.to_string(), r#macro)
+            })
+            .collect();
+        let tokens = Scanner::new(body.chars().collect()).scan_snippet().unwrap();
+        let parser = Parser::new(MarkdownDoc::new(String::new(), tokens)).with_macros(macros);
+        match parser.parse_with(ZetaFrontmatter::default()) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.error_type).collect(),
+        }
+    }
+
+    #[test]
+    fn macros() {
+        let macros = [
+            ("like", "Like"),
+            ("a", "{{b}}"),
+            ("b", "see {{a}}"),
+            ("c", "^[x"),
+        ];
+        assert_eq!(
+            errors_with_macros("{{like}} {{title}}", &macros),
+            Vec::new()
+        );
+        assert_eq!(
+            errors_with_macros("{{ {{ like }} ${{ x.y }}", &macros),
+            Vec::new()
+        );
+        assert_eq!(
+            errors_with_macros("{{unknown}}", &macros),
+            vec![ParseErrorType::UndefinedMacro("unknown".to_string())]
+        );
+        let cycle = ["a", "b", "a"].map(String::from).to_vec();
+        assert!(
+            errors_with_macros("{{a}}", &macros).contains(&ParseErrorType::RecursiveMacro(cycle))
+        );
+        assert_eq!(
+            errors_with_macros("{{c}}", &macros)[0],
+            ParseErrorType::InMacro {
+                name: "c".to_string(),
+                message: "Incomplete ']'.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn links_to_missing_articles() {
+        assert_eq!(
+            errors("see [the other](zeta:missing-article)\n"),
+            vec![ParseErrorType::ArticleNotFound(
+                "missing-article".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn unclosed_blocks_are_reported_where_they_open() {
+        let tokens = Scanner::new("a\n\n:::message info\nb\n".chars().collect())
+            .scan_snippet()
+            .unwrap();
+        let parser = Parser::new(MarkdownDoc::new(String::new(), tokens));
+        let errors = parser.parse_with(ZetaFrontmatter::default()).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ParseError {
+                error_type: ParseErrorType::CouldNotFindEndToken(_),
+                row: 3,
+                ..
+            }]
+        ));
+    }
+
+    /// Parses `body` in a project of its own with the given files.
+    fn parse_in_project(
+        project: &str,
+        body: &str,
+        files: &[(&str, &str)],
+    ) -> std::result::Result<ParsedMd, Vec<ParseError>> {
+        let root = std::env::temp_dir().join(format!("zeta-{}-{}", project, std::process::id()));
+        fs::create_dir_all(root.join(SNIPPETS_DIR)).unwrap();
+        for (path, content) in files {
+            fs::write(root.join(path), content).unwrap();
+        }
+        let tokens = Scanner::new(body.chars().collect()).scan_snippet().unwrap();
+        let mut parser = Parser::new(MarkdownDoc::new(String::new(), tokens));
+        parser.snippets = root.join(SNIPPETS_DIR);
+        let result = parser.parse_with(ZetaFrontmatter::default());
+        fs::remove_dir_all(root).unwrap();
+        result
+    }
+
+    #[test]
+    fn includes_are_spliced() {
+        let snippet = "## Author\n\nbio[^bio]\n\n[^bio]: note\n";
+        let included = parse_in_project(
+            "include",
+            "a[^a]\n\n<include path=\"snippets/bio.md\"/>\n\n[^a]: b\n",
+            &[("snippets/bio.md", snippet)],
+        )
+        .unwrap();
+        let direct =
+            parse_in_project("direct", &format!("a[^a]\n\n{}\n\n[^a]: b\n", snippet), &[]).unwrap();
+        assert_eq!(
+            format!("{:?}", included.elements),
+            format!("{:?}", direct.elements)
+        );
+    }
+
+    #[test]
+    fn includes_stay_in_snippets() {
+        let errors = parse_in_project(
+            "include-missing",
+            "a\n\n<include path=\"snippets/missing.md\"/>\n",
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ParseError {
+                error_type: ParseErrorType::IncludeNotFound(path),
+                row: 3,
+                ..
+            }] if path == "snippets/missing.md"
+        ));
+
+        for path in ["snippets/../secret.md", "/etc/hostname"] {
+            let errors = parse_in_project(
+                "include-outside",
+                &format!("<include path=\"{}\"/>\n", path),
+                &[("secret.md", "secret\n")],
+            )
+            .unwrap_err();
+            assert_eq!(
+                errors[0].error_type,
+                ParseErrorType::IncludeOutsideSnippets(path.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn include_cycles() {
+        let errors = parse_in_project(
+            "include-cycle",
+            "<include path=\"snippets/a.md\"/>\n",
+            &[
+                ("snippets/a.md", "a\n\n<include path=\"snippets/b.md\"/>\n"),
+                ("snippets/b.md", "b\n\n<include path=\"snippets/a.md\"/>\n"),
+            ],
+        )
+        .unwrap_err();
+        let messages = errors
+            .iter()
+            .map(|error| error.error_type.to_string())
+            .collect::<Vec<_>>();
+        assert!(messages.contains(
+            &"snippets/a.md: snippets/b.md: 'snippets/a.md' is included recursively.".to_string()
+        ));
+    }
+
+    #[test]
+    fn errors_in_includes_are_reported_in_the_snippet() {
+        let errors = parse_in_project(
+            "include-error",
+            "a\n\n<include path=\"snippets/a.md\"/>\n",
+            &[("snippets/a.md", "b\n\n:::message info\nc\n")],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &errors[0],
+            ParseError {
+                error_type: ParseErrorType::InInclude { path, .. },
+                row: 3,
+                ..
+            } if path == "snippets/a.md"
+        ));
+
+        let errors = parse_in_project(
+            "include-checks",
+            "## a\n\n<include path=\"snippets/a.md\"/>\n",
+            &[("snippets/a.md", "b[^b]\n\n#### c\n")],
+        )
+        .unwrap_err();
+        let errors = errors
+            .into_iter()
+            .map(|error| (error.error_type.to_string(), error.row))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "snippets/a.md: Footnote 'b' is referenced but not defined.".to_string(),
+                    1
+                ),
+                (
+                    "snippets/a.md: Heading level skips from 2 to 4.".to_string(),
+                    3
+                ),
+            ]
+        );
+    }
+}
@@ -2,6 +2,10 @@ pub fn zeta_message(message: &str) {
     println!("[🟢Zeta] {}", message);
 }
 
+pub fn zeta_warning(message: &str) {
+    println!("[🟡Zeta Warning] {}", message);
+}
+
 pub fn zeta_error(message: &str) {
     println!("[🛑Zeta Error] {}", message);
 }
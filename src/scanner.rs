@@ -1,14 +1,32 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, ops::Range};
+
+use clap::ValueEnum;
 
 use crate::{
     ast::{MarkdownDoc, TokenizedMd},
-    r#macro::{StringMacro, TokenizedMacro},
+    r#macro::{Platform, StringMacro, TokenizedMacro},
     token::{Token, TokenType},
 };
 
 const SEPARATOR: &str = "---\n";
 const MESSAGE_TAG: &str = "message";
 const DETAILS_TAG: &str = "details";
+const ONLY_TAG: &str = "only";
+const SLIDE_TAG: &str = "slide";
+const ONLY_TAG_BEGIN: &str = "<only ";
+const ONLY_TAG_END: &str = "</only>";
+const INCLUDE_TAG_BEGIN: &str = "<include ";
+const ARTICLE_LINK_BEGIN: &str = "](zeta:";
+const MACRO_REFERENCE_BEGIN: &str = "{{";
+const MACRO_REFERENCE_END: &str = "}}";
+const MERMAID_FENCE: &str = "```mermaid";
+const TOC_TAG: &str = "<toc/>";
+const HEADING_LEVEL_MAX: usize = 6;
+/// columns of indentation that make a line indented code
+const CODE_INDENT: usize = 4;
+/// `~~~` code fence
+const TILDE_FENCE: &str = "~~~";
+const IMAGE_SIZE_SEPARATOR: &str = " =";
 
 type Result<T> = std::result::Result<T, ScanError>;
 
@@ -33,6 +51,9 @@ impl ScanError {
 pub enum ScanErrorType {
     Incomplete(String),
     InvalidMacro,
+    InvalidImageSize(String),
+    InvalidPlatform(String),
+    InvalidInclude(String),
 }
 
 impl Display for ScanErrorType {
@@ -40,22 +61,52 @@ impl Display for ScanErrorType {
         match self {
             ScanErrorType::Incomplete(string) => write!(f, "Incomplete '{}'.", string),
             ScanErrorType::InvalidMacro => write!(f, "Invalid macro."),
+            ScanErrorType::InvalidPlatform(platform) => {
+                write!(f, "Invalid platform '{}'. Expected 'zenn' or 'qiita'.", platform)
+            }
+            ScanErrorType::InvalidInclude(attributes) => write!(
+                f,
+                "Invalid include '{}'. Expected '<include path=\"...\"/>'.",
+                attributes
+            ),
+            ScanErrorType::InvalidImageSize(size) => {
+                write!(f, "Invalid image size '{}'. Expected '=<width>x'.", size)
+            }
         }
     }
 }
 
 impl Error for ScanErrorType {}
 
+/// Kinds of source ranges that the scanner leaves as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// code spans and code blocks, including ```` ```mermaid ````
+    Code,
+    /// YAML between `<macro>` and `</macro>`
+    Macro,
+}
+
+/// Ranges of the source with their kinds
+pub type Regions = Vec<(Range<usize>, Region)>;
+
 #[derive(Debug, Clone)]
 pub struct Scanner {
     source: Vec<char>,
 
     current: usize,
     start: usize,
+    /// start of the token being scanned, right after the text before it
+    token_start: usize,
 
     row: usize,
     col: usize,
 
+    /// position of the source in the outermost source, for the scanners of nested text
+    offset: usize,
+    /// character ranges of the outermost source left as they are
+    regions: Regions,
+
     tokens: Vec<Token>,
     errors: Vec<ScanError>,
 }
@@ -70,8 +121,11 @@ impl Scanner {
             source,
             current: 0,
             start: 0,
+            token_start: 0,
             row,
             col,
+            offset: 0,
+            regions: Vec::new(),
             tokens: Vec::new(),
             errors: Vec::new(),
         }
@@ -86,19 +140,50 @@ impl Scanner {
             }
         };
 
-        self.source.insert(self.current, '\n');
+        let body = self.scan_from_line_start()?;
 
-        let mut body = self.scan_body()?;
-        if let Some(first) = body.first_mut() {
-            if let TokenType::Text(text) = &mut first.token_type {
-                *text = text.get(1..).unwrap_or_default().to_string();
-            }
+        Ok(MarkdownDoc::new(frontmatter, body))
+    }
+
+    /// Scans a markdown file without frontmatter.
+    pub fn scan_snippet(self) -> std::result::Result<Vec<Token>, Vec<ScanError>> {
+        self.scan_from_line_start()
+    }
+
+    /// Scans a body whose first line may start with a block element.
+    fn scan_from_line_start(mut self) -> std::result::Result<Vec<Token>, Vec<ScanError>> {
+        if let Err(error) = self.block_element() {
+            self.errors.push(error);
         }
+        self.scan_body()
+    }
 
-        Ok(MarkdownDoc::new(frontmatter, body))
+    /// Scans a markdown file without frontmatter like `scan_snippet`, and also returns the ranges
+    /// of code and macros in it as character offsets.
+    pub fn scan_regions(mut self) -> std::result::Result<(Vec<Token>, Regions), Vec<ScanError>> {
+        if let Err(error) = self.block_element() {
+            self.errors.push(error);
+        }
+        self.scan_rest();
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok((self.tokens, self.regions))
     }
 
-    fn scan_body(mut self) -> std::result::Result<Vec<Token>, Vec<ScanError>> {
+    pub fn scan_body(mut self) -> std::result::Result<Vec<Token>, Vec<ScanError>> {
+        self.scan_rest();
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(self.tokens)
+    }
+
+    fn scan_rest(&mut self) {
         while !self.is_at_end() {
             if let Err(error) = self.scan() {
                 self.errors.push(error);
@@ -106,12 +191,31 @@ impl Scanner {
         }
 
         self.collect_text();
+    }
 
-        if !self.errors.is_empty() {
-            return Err(self.errors);
+    /// Scans text nested in a token, like the body of `<only>`, which starts at `start`.
+    /// The errors are recorded and give `None`.
+    fn scan_nested(
+        &mut self,
+        text: &str,
+        start: usize,
+        row: usize,
+        col: usize,
+    ) -> Option<Vec<Token>> {
+        let mut scanner = Scanner::with_row_col(text.chars().collect(), row, col);
+        scanner.offset = self.offset + start;
+        scanner.scan_rest();
+        self.regions.append(&mut scanner.regions);
+        if !scanner.errors.is_empty() {
+            self.errors.append(&mut scanner.errors);
+            return None;
         }
+        Some(scanner.tokens)
+    }
 
-        Ok(self.tokens)
+    fn add_region(&mut self, range: Range<usize>, region: Region) {
+        let range = self.offset + range.start..self.offset + range.end;
+        self.regions.push((range, region));
     }
 
     fn scan_frontmatter(&mut self) -> Result<String> {
@@ -142,7 +246,7 @@ impl Scanner {
                 self.expect_string("![");
                 self.delete_buffer();
 
-                self.extract_until("]")?;
+                self.extract_until_balanced('[', ']')?;
 
                 let alt = self.consume_buffer();
                 self.expect_string("](");
@@ -150,12 +254,36 @@ impl Scanner {
 
                 self.extract_until(")")?;
 
+                let (row, col) = (self.row, self.col);
                 let url = self.consume_buffer();
                 self.expect_string(")");
                 self.delete_buffer();
 
-                self.tokens
-                    .push(self.make_token(TokenType::Image { alt, url }));
+                let (url, width) = match url.split_once(IMAGE_SIZE_SEPARATOR) {
+                    Some((url, size)) => {
+                        let Some(width) = size
+                            .strip_suffix('x')
+                            .and_then(|width| width.parse::<usize>().ok())
+                        else {
+                            return Err(ScanError::new(
+                                ScanErrorType::InvalidImageSize(size.to_string()),
+                                row,
+                                col,
+                            ));
+                        };
+                        (url.to_string(), Some(width))
+                    }
+                    None => (url, None),
+                };
+
+                let caption = self.scan_image_caption();
+
+                self.tokens.push(self.make_token(TokenType::Image {
+                    alt,
+                    url,
+                    width,
+                    caption,
+                }));
             }
 
             '@' => {
@@ -167,7 +295,7 @@ impl Scanner {
                 self.expect_string("@[");
                 self.delete_buffer();
 
-                self.extract_until("]")?;
+                self.extract_until_balanced('[', ']')?;
 
                 let card_type = self.consume_buffer();
                 self.expect_string("](");
@@ -190,22 +318,30 @@ impl Scanner {
                 }
                 self.collect_text();
                 self.expect_string("^[");
+                let (start, row, col) = (self.current, self.row, self.col);
                 self.delete_buffer();
-                self.extract_until("]")?;
+                self.extract_until_balanced('[', ']')?;
                 let footnote = self.consume_buffer();
                 self.expect_string("]");
                 self.delete_buffer();
+
+                let Some(footnote) = self.scan_nested(&footnote, start, row, col) else {
+                    return Ok(());
+                };
                 self.tokens
                     .push(self.make_token(TokenType::InlineFootnote(footnote)));
             }
 
             '[' => {
+                if self.matches_article_link() {
+                    return self.scan_article_link();
+                }
                 if !self.matches_keyword("[^") {
                     self.advance();
                     return Ok(());
                 }
-                let start = self.current;
                 self.collect_text();
+                let (start, row, col) = (self.current, self.row, self.col);
                 self.expect_string("[^");
                 self.delete_buffer();
                 self.extract_until("]")?;
@@ -213,34 +349,111 @@ impl Scanner {
                 self.expect_string("]");
                 self.delete_buffer();
                 if self.matches_keyword(":") {
-                    self.start = start;
+                    self.expect_string(":");
+                    self.consume_spaces();
+                    self.delete_buffer();
+                    let marker = self.source[start..self.current].iter().collect();
+
+                    // the marker and the line break are kept in the body, like the `## ` of a heading
+                    let body_start = self.tokens.len();
+                    self.tokens.push(self.make_trivia(marker, row, col));
+                    while !self.is_at_end() && self.peek() != Some('\n') {
+                        self.scan()?;
+                    }
+                    self.collect_text();
+                    let (row, col) = (self.row, self.col);
+                    let line_break = self.expect_string("\n");
+                    if line_break {
+                        self.delete_buffer();
+                        self.tokens
+                            .push(self.make_trivia("\n".to_string(), row, col));
+                    }
+                    let body = self.tokens.split_off(body_start);
+                    self.token_start = start;
+                    self.tokens.push(self.make_token(TokenType::FootnoteDefinition {
+                        name: footnote,
+                        body,
+                    }));
+
+                    if line_break {
+                        self.block_element()?;
+                    }
                     return Ok(());
                 }
                 self.tokens
                     .push(self.make_token(TokenType::Footnote(footnote)));
             }
 
+            '{' => {
+                // other braces, like `{{ user.name }}` of a template language, are text
+                let Some(len) = self.macro_reference_len() else {
+                    self.advance();
+                    return Ok(());
+                };
+                self.collect_text();
+                self.expect_string(MACRO_REFERENCE_BEGIN);
+                self.delete_buffer();
+                (0..len).for_each(|_| {
+                    self.advance();
+                });
+                let name = self.consume_buffer();
+                self.expect_string(MACRO_REFERENCE_END);
+                self.delete_buffer();
+                self.tokens
+                    .push(self.make_token(TokenType::MacroReference(name)));
+            }
+
             '`' => {
-                if self.matches_keyword("```") {
+                if self.matches_keyword(MERMAID_FENCE) {
+                    self.collect_text();
+                    self.expect_string(MERMAID_FENCE);
+                    self.extract_until("\n")?;
+                    self.expect_string("\n");
+                    self.delete_buffer();
+                    self.extract_until("```")?;
+                    let code = self.consume_buffer();
+                    self.expect_string("```");
+                    self.delete_buffer();
+                    self.add_region(self.token_start..self.current, Region::Code);
+                    self.tokens.push(self.make_token(TokenType::Mermaid(code)));
+                } else if self.matches_keyword("```") {
+                    let start = self.current;
                     self.expect_string("```");
                     self.extract_until("```")?;
                     self.expect_string("```");
+                    self.add_region(start..self.current, Region::Code);
                 } else {
+                    let start = self.current;
                     self.expect_string("`");
                     self.extract_until("`")?;
                     self.expect_string("`");
+                    self.add_region(start..self.current, Region::Code);
                 }
             }
             '<' => {
+                if self.matches_keyword(ONLY_TAG_BEGIN) {
+                    return self.scan_only_tag();
+                }
+                if self.matches_keyword(INCLUDE_TAG_BEGIN) {
+                    return self.scan_include_tag();
+                }
+                if self.matches_keyword(TOC_TAG) {
+                    self.collect_text();
+                    self.expect_string(TOC_TAG);
+                    self.delete_buffer();
+                    self.tokens.push(self.make_token(TokenType::Toc));
+                    return Ok(());
+                }
                 if !self.matches_keyword("<macro>") {
                     self.advance();
                     return Ok(());
                 }
                 self.collect_text();
                 self.expect_string("<macro>");
-                let (row, col) = (self.row, self.col);
+                let (start, row, col) = (self.current, self.row, self.col);
                 self.delete_buffer();
                 self.extract_until("</macro>")?;
+                self.add_region(start..self.current, Region::Macro);
 
                 let body = self.consume_buffer();
                 self.expect_string("</macro>");
@@ -300,8 +513,23 @@ impl Scanner {
             return Ok(());
         };
 
+        // code blocks are left as text, like the contents of ``` fences
+        if self.is_indented_code_start() {
+            let start = self.current;
+            self.skip_indented_code();
+            self.add_region(start..self.current, Region::Code);
+            return Ok(());
+        }
+
         self.consume_spaces();
 
+        if self.matches_keyword(TILDE_FENCE) {
+            let start = self.current;
+            self.skip_tilde_fence();
+            self.add_region(start..self.current, Region::Code);
+            return Ok(());
+        }
+
         match c_next {
             'h' => {
                 if !(self.matches_keyword("https://") || self.matches_keyword("http://")) {
@@ -324,6 +552,7 @@ impl Scanner {
                 let url = self.consume_buffer();
                 self.tokens.push(self.make_token(TokenType::Url(url)));
             }
+            '#' => return self.scan_heading(),
             ':' => {
                 if !self.matches_keyword(":::") {
                     return Ok(());
@@ -357,6 +586,26 @@ impl Scanner {
                     self.delete_buffer();
                     self.tokens
                         .push(self.make_token(TokenType::DetailsBegin { level, title }));
+                } else if self.matches_keyword(ONLY_TAG) {
+                    self.expect_string(ONLY_TAG);
+                    self.consume_spaces();
+                    let (row, col) = (self.row, self.col);
+                    self.delete_buffer();
+                    self.extract_until("\n")?;
+                    let platforms = self.consume_buffer();
+                    let platforms = parse_platforms(&platforms, row, col)?;
+                    self.tokens.push(
+                        self.make_token(TokenType::ConditionalBegin { level, platforms }),
+                    );
+                } else if self.matches_keyword(SLIDE_TAG) {
+                    self.expect_string(SLIDE_TAG);
+                    self.consume_spaces();
+                    self.delete_buffer();
+                    self.extract_until("\n")?;
+                    let title = Some(self.consume_buffer()).filter(|title| !title.is_empty());
+                    self.delete_buffer();
+                    self.tokens
+                        .push(self.make_token(TokenType::SlideBreak { title }));
                 } else {
                     self.delete_buffer();
                     self.tokens
@@ -369,11 +618,320 @@ impl Scanner {
         Ok(())
     }
 
+    /// Advances over a `~~~` code block up to its closing fence or the end of the source.
+    fn skip_tilde_fence(&mut self) {
+        let mut len = 0;
+        while self.peek() == Some('~') {
+            self.advance();
+            len += 1;
+        }
+        self.extract_until_unchecked("\n");
+
+        while !self.is_at_end() {
+            self.advance();
+            let line = self.current;
+            self.extract_until_unchecked("\n");
+
+            let content = self.source[line..self.current].iter().collect::<String>();
+            let indent = self.line_indent(line);
+            let fence = content.trim();
+            if indent < CODE_INDENT && fence.len() >= len && fence.chars().all(|c| c == '~') {
+                break;
+            }
+        }
+    }
+
+    /// Whether the line at the current position starts indented code:
+    /// indented by 4 columns after a blank line, and not continuing a list item.
+    fn is_indented_code_start(&self) -> bool {
+        if self.is_blank_line(self.current) || self.line_indent(self.current) < CODE_INDENT {
+            return false;
+        }
+        let Some(mut line) = self.previous_line(self.current) else {
+            return true;
+        };
+        if !self.is_blank_line(line) {
+            return false;
+        }
+
+        while let Some(previous) = self.previous_line(line) {
+            line = previous;
+            if !self.is_blank_line(line) && self.line_indent(line) < CODE_INDENT {
+                return !self.is_list_item(line);
+            }
+        }
+        true
+    }
+
+    /// Advances over indented code, including blank lines between its lines.
+    fn skip_indented_code(&mut self) {
+        loop {
+            self.extract_until_unchecked("\n");
+
+            let mut next = self.current + 1;
+            while next < self.source.len() && self.is_blank_line(next) {
+                next = self.line_end(next) + 1;
+            }
+            if next >= self.source.len() || self.line_indent(next) < CODE_INDENT {
+                return;
+            }
+            while self.current < next {
+                self.advance();
+            }
+        }
+    }
+
+    /// Index of the `\n` ending the line that starts at `start`, or the end of the source.
+    fn line_end(&self, start: usize) -> usize {
+        self.source[start..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.source.len(), |end| start + end)
+    }
+
+    /// Start of the line before the line that starts at `start`.
+    fn previous_line(&self, start: usize) -> Option<usize> {
+        if start == 0 {
+            return None;
+        }
+        let start = self.source[..start - 1]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |end| end + 1);
+        Some(start)
+    }
+
+    fn is_blank_line(&self, start: usize) -> bool {
+        self.source[start..self.line_end(start)]
+            .iter()
+            .all(|c| c.is_whitespace())
+    }
+
+    /// Columns of indentation of the line that starts at `start`, with tab stops of 4.
+    fn line_indent(&self, start: usize) -> usize {
+        let mut indent = 0;
+        for c in &self.source[start..self.line_end(start)] {
+            match c {
+                ' ' => indent += 1,
+                '\t' => indent += CODE_INDENT - indent % CODE_INDENT,
+                _ => break,
+            }
+        }
+        indent
+    }
+
+    /// Whether the line that starts at `start` is a list item like `- item` or `1. item`.
+    fn is_list_item(&self, start: usize) -> bool {
+        let line = self.source[start..self.line_end(start)]
+            .iter()
+            .collect::<String>();
+        let line = line.trim_start();
+        let marker = match line.find(|c: char| !c.is_ascii_digit()) {
+            Some(0) => line.strip_prefix(['-', '*', '+']),
+            Some(digits) if digits <= 9 => line[digits..].strip_prefix(['.', ')']),
+            _ => None,
+        };
+        marker.is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+    }
+
+    /// ATX heading `## text`
+    fn scan_heading(&mut self) -> Result<()> {
+        let mut level = 0;
+        while self.source.get(self.current + level) == Some(&'#') {
+            level += 1;
+        }
+        let after = self.source.get(self.current + level);
+        if level > HEADING_LEVEL_MAX || !matches!(after, Some(' ') | Some('\t')) {
+            return Ok(());
+        }
+
+        self.collect_text();
+        let (heading_row, heading_col) = (self.row, self.col);
+        (0..level).for_each(|_| {
+            self.advance();
+        });
+        self.extract_while(' ');
+        self.extract_while('\t');
+        let prefix = self.consume_buffer();
+        let prefix = self.make_trivia(prefix, heading_row, heading_col);
+        let (start, row, col) = (self.current, self.row, self.col);
+        self.extract_until_unchecked("\n");
+        let line = self.consume_buffer();
+        let body = line.trim_end();
+
+        let Some(mut text) = self.scan_nested(body, start, row, col) else {
+            return Ok(());
+        };
+        text.insert(0, prefix);
+        if body.len() < line.len() {
+            text.push(self.make_trivia(line[body.len()..].to_string(), self.row, self.col));
+        }
+        let mut heading = self.make_token(TokenType::Heading { level, text });
+        (heading.row, heading.col) = (heading_row, heading_col);
+        self.tokens.push(heading);
+
+        Ok(())
+    }
+
+    /// `<only zenn>...</only>`
+    fn scan_only_tag(&mut self) -> Result<()> {
+        self.collect_text();
+        self.expect_string(ONLY_TAG_BEGIN);
+        let (row, col) = (self.row, self.col);
+        self.delete_buffer();
+        self.extract_until(">")?;
+        let platforms = self.consume_buffer();
+        let platforms = parse_platforms(&platforms, row, col)?;
+        self.expect_string(">");
+
+        let (start, row, col) = (self.current, self.row, self.col);
+        self.delete_buffer();
+        self.extract_until_closing(ONLY_TAG_BEGIN, ONLY_TAG_END)?;
+        let body = self.consume_buffer();
+        self.expect_string(ONLY_TAG_END);
+        self.delete_buffer();
+
+        let Some(body) = self.scan_nested(&body, start, row, col) else {
+            return Ok(());
+        };
+        self.tokens
+            .push(self.make_token(TokenType::Conditional { platforms, body }));
+
+        Ok(())
+    }
+
+    /// Length of the name of the `{{name}}` at the current position, if there is one.
+    fn macro_reference_len(&mut self) -> Option<usize> {
+        if !self.matches_keyword(MACRO_REFERENCE_BEGIN) {
+            return None;
+        }
+        let start = self.current + MACRO_REFERENCE_BEGIN.len();
+        let len = self.source[start..]
+            .iter()
+            .take_while(|c| is_macro_name_char(**c))
+            .count();
+        let end = MACRO_REFERENCE_END.chars().collect::<Vec<char>>();
+        let closed = self.source.get(start + len..start + len + end.len()) == Some(&end[..]);
+        (len > 0 && closed).then_some(len)
+    }
+
+    fn matches_article_link(&self) -> bool {
+        let mut depth: usize = 0;
+        for (i, c) in self.source.iter().enumerate().skip(self.current + 1) {
+            match c {
+                '[' => depth += 1,
+                ']' if depth == 0 => {
+                    let keyword = ARTICLE_LINK_BEGIN.chars().collect::<Vec<char>>();
+                    return self.source.get(i..i + keyword.len()) == Some(&keyword[..]);
+                }
+                ']' => depth -= 1,
+                '\n' => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
+    /// `[text](zeta:other-article)`
+    fn scan_article_link(&mut self) -> Result<()> {
+        self.collect_text();
+        self.expect_string("[");
+        let (start, row, col) = (self.current, self.row, self.col);
+        self.delete_buffer();
+        self.extract_until_balanced('[', ']')?;
+        let text = self.consume_buffer();
+        self.expect_string(ARTICLE_LINK_BEGIN);
+        self.delete_buffer();
+        self.extract_until(")")?;
+        let slug = self.consume_buffer().trim().to_string();
+        self.expect_string(")");
+        self.delete_buffer();
+
+        let Some(text) = self.scan_nested(&text, start, row, col) else {
+            return Ok(());
+        };
+        self.tokens
+            .push(self.make_token(TokenType::ArticleLink { text, slug }));
+
+        Ok(())
+    }
+
+    /// `<include path="snippets/author-bio.md"/>`
+    fn scan_include_tag(&mut self) -> Result<()> {
+        self.collect_text();
+        self.expect_string(INCLUDE_TAG_BEGIN);
+        let (row, col) = (self.row, self.col);
+        self.delete_buffer();
+        self.extract_until(">")?;
+        let attributes = self.consume_buffer();
+        self.expect_string(">");
+        self.delete_buffer();
+
+        let path = attributes
+            .trim()
+            .trim_end_matches('/')
+            .trim()
+            .strip_prefix("path=")
+            .and_then(|path| path.strip_prefix('"'))
+            .and_then(|path| path.strip_suffix('"'))
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| {
+                ScanError::new(ScanErrorType::InvalidInclude(attributes.clone()), row, col)
+            })?;
+
+        self.tokens
+            .push(self.make_token(TokenType::Include(path.to_string())));
+
+        Ok(())
+    }
+
+    /// `*caption*` on the line directly under an image
+    fn scan_image_caption(&mut self) -> Option<String> {
+        if !self.matches_keyword("\n*") || self.matches_keyword("\n**") {
+            return None;
+        }
+
+        let (pos, row, col) = (self.current, self.row, self.col);
+        self.expect_string("\n*");
+        self.delete_buffer();
+        self.extract_until_unchecked("*");
+        let caption = self.consume_buffer();
+
+        // the whole line has to be a single emphasis, not a list item like `* item *x*`
+        let is_caption = self.matches_keyword("*")
+            && !caption.is_empty()
+            && !caption.contains('\n')
+            && caption.trim() == caption
+            && matches!(self.source.get(self.current + 1), None | Some('\n'));
+        if !is_caption {
+            self.current = pos;
+            self.start = pos;
+            self.row = row;
+            self.col = col;
+            return None;
+        }
+
+        self.expect_string("*");
+        self.delete_buffer();
+        Some(caption)
+    }
+
     fn make_token(&self, token_type: TokenType) -> Token {
         Token {
             token_type,
             row: self.row,
             col: self.col,
+            source: self.source[self.token_start..self.current].iter().collect(),
+        }
+    }
+
+    /// Makes a token of the layout text `text`, like the `## ` of a heading.
+    fn make_trivia(&self, text: String, row: usize, col: usize) -> Token {
+        Token {
+            token_type: TokenType::Trivia(text.clone()),
+            row,
+            col,
+            source: text,
         }
     }
 
@@ -434,7 +992,15 @@ impl Scanner {
 
     fn collect_text(&mut self) {
         let text = self.consume_buffer();
-        self.tokens.push(self.make_token(TokenType::Text(text)));
+        if !text.is_empty() {
+            self.tokens.push(Token {
+                token_type: TokenType::Text(text.clone()),
+                row: self.row,
+                col: self.col,
+                source: text,
+            });
+        }
+        self.token_start = self.current;
     }
 
     fn extract_until(&mut self, end: &str) -> Result<()> {
@@ -458,6 +1024,70 @@ impl Scanner {
         Ok(())
     }
 
+    /// Extracts until `close` that is not paired with a preceding `open`.
+    fn extract_until_balanced(&mut self, open: char, close: char) -> Result<()> {
+        let (pos, row, col) = (self.current, self.row, self.col);
+        let mut depth: usize = 0;
+
+        while let Some(c) = self.peek() {
+            if c == close {
+                if depth == 0 {
+                    return Ok(());
+                }
+                depth -= 1;
+            } else if c == open {
+                depth += 1;
+            }
+            self.advance();
+        }
+
+        self.current = pos;
+        self.row = row;
+        self.col = col;
+
+        self.advance();
+
+        Err(ScanError::new(
+            ScanErrorType::Incomplete(close.to_string()),
+            self.row,
+            self.col,
+        ))
+    }
+
+    /// Extracts until `close` that is not paired with a preceding `open`, like the `</only>` of
+    /// an `<only>` with nested ones.
+    fn extract_until_closing(&mut self, open: &str, close: &str) -> Result<()> {
+        let (pos, row, col) = (self.current, self.row, self.col);
+        let mut depth: usize = 0;
+
+        while !self.is_at_end() {
+            if self.matches_keyword(close) {
+                if depth == 0 {
+                    return Ok(());
+                }
+                depth -= 1;
+                self.expect_string(close);
+                continue;
+            }
+            if self.matches_keyword(open) {
+                depth += 1;
+            }
+            self.advance();
+        }
+
+        self.current = pos;
+        self.row = row;
+        self.col = col;
+
+        self.advance();
+
+        Err(ScanError::new(
+            ScanErrorType::Incomplete(close.to_string()),
+            self.row,
+            self.col,
+        ))
+    }
+
     fn extract_while(&mut self, char: char) {
         while self.peek() == Some(char) && !self.is_at_end() {
             self.advance();
@@ -477,3 +1107,322 @@ impl Scanner {
         self.extract_while(' ');
     }
 }
+
+/// Macro and variable names are ASCII alphanumerics, `_` and `-`.
+fn is_macro_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Parses a list of platforms separated by spaces or commas.
+fn parse_platforms(platforms: &str, row: usize, col: usize) -> Result<Vec<Platform>> {
+    let platforms = platforms
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|platform| !platform.is_empty())
+        .map(|platform| {
+            Platform::from_str(platform, true).map_err(|_| {
+                ScanError::new(
+                    ScanErrorType::InvalidPlatform(platform.to_string()),
+                    row,
+                    col,
+                )
+            })
+        })
+        .collect::<Result<Vec<Platform>>>()?;
+
+    if platforms.is_empty() {
+        return Err(ScanError::new(
+            ScanErrorType::InvalidPlatform(String::new()),
+            row,
+            col,
+        ));
+    }
+
+    Ok(platforms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Vec<TokenType> {
+        Scanner::new(source.chars().collect())
+            .scan_snippet()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect()
+    }
+
+    fn assert_definition(token: &TokenType, name: &str, body: &str) {
+        let TokenType::FootnoteDefinition {
+            name: actual_name,
+            body: actual_body,
+        } = token
+        else {
+            panic!("not a footnote definition: {:?}", token);
+        };
+        assert_eq!(actual_name, name);
+        let actual_body: Vec<TokenType> = actual_body
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect();
+        // the marker and the line break are kept as trivia
+        let expected = vec![
+            TokenType::Trivia(format!("[^{}]: ", name)),
+            TokenType::Text(body.to_string()),
+            TokenType::Trivia("\n".to_string()),
+        ];
+        assert_eq!(actual_body, expected);
+    }
+
+    #[test]
+    fn footnote_definition_comes_before_the_next_block() {
+        let blocks = [
+            (
+                "https://example.com",
+                TokenType::Url("https://example.com".to_string()),
+            ),
+            (
+                ":::message info",
+                TokenType::MessageBegin {
+                    level: 0,
+                    r#type: "info".to_string(),
+                },
+            ),
+            (
+                ":::details title",
+                TokenType::DetailsBegin {
+                    level: 0,
+                    title: "title".to_string(),
+                },
+            ),
+            (
+                ":::only qiita",
+                TokenType::ConditionalBegin {
+                    level: 0,
+                    platforms: vec![Platform::Qiita],
+                },
+            ),
+            (":::slide", TokenType::SlideBreak { title: None }),
+            (":::", TokenType::MessageOrDetailsEnd { level: 0 }),
+        ];
+
+        for (block, token) in blocks {
+            let tokens = scan(&format!("[^a]: body of a\n{}\n", block));
+            assert_definition(&tokens[0], "a", "body of a");
+            assert_eq!(tokens[1], token, "{}", block);
+        }
+
+        let tokens = scan("[^a]: body of a\n## heading\n");
+        assert_definition(&tokens[0], "a", "body of a");
+        assert!(matches!(tokens[1], TokenType::Heading { level: 2, .. }));
+    }
+
+    #[test]
+    fn caption_is_a_whole_line_of_emphasis() {
+        let caption = |source: &str| match &scan(source)[0] {
+            TokenType::Image { caption, .. } => caption.clone(),
+            token => panic!("not an image: {:?}", token),
+        };
+
+        assert_eq!(
+            caption("![a](a.png)\n*caption*\n"),
+            Some("caption".to_string())
+        );
+        assert_eq!(
+            caption("![a](a.png)\n*caption*"),
+            Some("caption".to_string())
+        );
+        assert_eq!(caption("![a](a.png)\n* item *x*\n"), None);
+        assert_eq!(caption("![a](a.png)\n*caption* and more\n"), None);
+        assert_eq!(caption("![a](a.png)\n**bold**\n"), None);
+    }
+
+    #[test]
+    fn brackets_are_balanced() {
+        let tokens = scan("a^[see [b](https://b.example) and [^c]] d");
+        let TokenType::InlineFootnote(footnote) = &tokens[1] else {
+            panic!("not an inline footnote: {:?}", tokens[1]);
+        };
+        let footnote: Vec<TokenType> = footnote
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect();
+        assert_eq!(
+            footnote,
+            vec![
+                TokenType::Text("see [b](https://b.example) and ".to_string()),
+                TokenType::Footnote("c".to_string()),
+            ]
+        );
+        assert_eq!(tokens[2], TokenType::Text(" d".to_string()));
+
+        assert!(matches!(
+            &scan("![a [b] c](a.png)")[0],
+            TokenType::Image { alt, .. } if alt == "a [b] c"
+        ));
+        assert!(matches!(
+            &scan("@[card [x]](https://example.com)")[0],
+            TokenType::LinkCard { card_type, .. } if card_type == "card [x]"
+        ));
+
+        let errors = Scanner::new("^[a [b]".chars().collect())
+            .scan_snippet()
+            .unwrap_err();
+        assert_eq!(
+            errors[0].error_type,
+            ScanErrorType::Incomplete("]".to_string())
+        );
+    }
+
+    #[test]
+    fn only_tags_nest() {
+        let tokens = scan("<only zenn>a <only qiita>b</only> c</only> d");
+        let TokenType::Conditional { platforms, body } = &tokens[0] else {
+            panic!("not a conditional: {:?}", tokens[0]);
+        };
+        assert_eq!(platforms, &vec![Platform::Zenn]);
+        assert_eq!(body.len(), 3);
+        assert!(matches!(
+            &body[1].token_type,
+            TokenType::Conditional { platforms, .. } if platforms == &vec![Platform::Qiita]
+        ));
+        assert_eq!(body[2].token_type, TokenType::Text(" c".to_string()));
+        assert_eq!(tokens[1], TokenType::Text(" d".to_string()));
+
+        let errors = Scanner::new("<only zenn>a <only qiita>b</only>".chars().collect())
+            .scan_snippet()
+            .unwrap_err();
+        assert_eq!(
+            errors[0].error_type,
+            ScanErrorType::Incomplete(ONLY_TAG_END.to_string())
+        );
+    }
+
+    #[test]
+    fn macro_references_are_names_in_braces() {
+        assert_eq!(
+            scan("a {{like}} {{zenn_url}} {{a-1}}"),
+            vec![
+                TokenType::Text("a ".to_string()),
+                TokenType::MacroReference("like".to_string()),
+                TokenType::Text(" ".to_string()),
+                TokenType::MacroReference("zenn_url".to_string()),
+                TokenType::Text(" ".to_string()),
+                TokenType::MacroReference("a-1".to_string()),
+            ]
+        );
+        for text in [
+            "{{",
+            "a {{like",
+            "{{ like }}",
+            "{{}}",
+            "{{ user.name }}",
+            "{{#if ok}}",
+            "${{ secrets.TOKEN }}",
+            "{{like}",
+        ] {
+            assert_eq!(
+                scan(text),
+                vec![TokenType::Text(text.to_string())],
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            scan("{{{like}}}"),
+            vec![
+                TokenType::Text("{".to_string()),
+                TokenType::MacroReference("like".to_string()),
+                TokenType::Text("}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn image_width() {
+        assert_eq!(
+            scan("![a](a.png =250x)"),
+            vec![TokenType::Image {
+                alt: "a".to_string(),
+                url: "a.png".to_string(),
+                width: Some(250),
+                caption: None,
+            }]
+        );
+        for size in ["250", "x", "250px", "-1x"] {
+            let errors = Scanner::new(format!("![a](a.png ={})", size).chars().collect())
+                .scan_snippet()
+                .unwrap_err();
+            assert_eq!(
+                errors[0].error_type,
+                ScanErrorType::InvalidImageSize(size.to_string())
+            );
+        }
+    }
+
+    fn heading_levels(source: &str) -> Vec<usize> {
+        scan(source)
+            .into_iter()
+            .filter_map(|token| match token {
+                TokenType::Heading { level, .. } => Some(level),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_headings_in_tilde_fences() {
+        assert_eq!(
+            heading_levels("~~~sh\n# comment\n~~~\n## heading\n"),
+            vec![2]
+        );
+        assert_eq!(
+            heading_levels("~~~~\n~~~\n# comment\n~~~~\n## heading\n"),
+            vec![2]
+        );
+        assert_eq!(heading_levels("~~~\n# comment\n"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn no_headings_in_indented_code() {
+        assert_eq!(
+            heading_levels("text\n\n    # comment\n\n    ## comment\n## heading\n"),
+            vec![2]
+        );
+        assert_eq!(heading_levels("\t# comment\n"), Vec::<usize>::new());
+        // a paragraph continuation is not code
+        assert_eq!(heading_levels("text\n    # text\n"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn list_continuations_are_not_code() {
+        for source in [
+            "- item\n\n    {{like}}\n",
+            "1. item\n\n    text\n\n    {{like}}\n",
+        ] {
+            assert!(
+                scan(source).contains(&TokenType::MacroReference("like".to_string())),
+                "{}",
+                source
+            );
+        }
+        assert!(!scan("text\n\n    {{like}}\n")
+            .contains(&TokenType::MacroReference("like".to_string())));
+    }
+
+    #[test]
+    fn tokens_keep_their_source() {
+        let source = "## Title  \n\nsome *text* and [^a] and ^[inline] {{title}}\n\n\
+            https://example.com\n\n@[card](https://example.com)\n\n\
+            ![alt](https://example.com/a.png =250x)\n*caption*\n\n```mermaid\ngraph TD\n```\n\n\
+            :::details  title\nbody <toc/>\n:::\n\n::::message alert\nwarning\n::::\n\n\
+            <only zenn>z</only>\n:::only qiita\nq\n:::\n:::slide  t\n[^a]:   note  \nend\n\
+            <macro>\nzenn: a\nqiita: b\n</macro>\n";
+        let tokens = Scanner::new(source.chars().collect())
+            .scan_snippet()
+            .unwrap();
+        let written: String = tokens.iter().map(|token| token.source.as_str()).collect();
+        assert_eq!(written, source);
+    }
+}
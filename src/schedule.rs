@@ -0,0 +1,154 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Zenn interprets `published_at` in JST.
+const JST_OFFSET_SECS: i64 = 9 * 60 * 60;
+
+/// Parses `YYYY-MM-DD` or `YYYY-MM-DD HH:MM` in JST into a Unix timestamp.
+pub fn parse_published_at(published_at: &str) -> Option<i64> {
+    let published_at = published_at.trim();
+    let (date, time) = match published_at.split_once(' ') {
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (published_at, None),
+    };
+
+    let mut date = date.split('-');
+    let year: i64 = parse_digits(date.next()?, 4)?;
+    let month: i64 = parse_digits(date.next()?, 2)?;
+    let day: i64 = parse_digits(date.next()?, 2)?;
+    if date.next().is_some()
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+    {
+        return None;
+    }
+
+    let (hour, minute) = match time {
+        Some(time) => {
+            let (hour, minute) = time.split_once(':')?;
+            (parse_digits(hour, 2)?, parse_digits(minute, 2)?)
+        }
+        None => (0, 0),
+    };
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 24 * 60 * 60 + hour * 60 * 60 + minute * 60 - JST_OFFSET_SECS)
+}
+
+/// Whether the reserved publishing time has passed.
+pub fn is_due(published_at: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default();
+
+    is_due_at(published_at, now)
+}
+
+/// Whether the reserved publishing time has passed at `now`, a Unix timestamp.
+fn is_due_at(published_at: &str, now: i64) -> bool {
+    parse_published_at(published_at).is_some_and(|published_at| published_at <= now)
+}
+
+fn parse_digits(digits: &str, len: usize) -> Option<i64> {
+    if digits.len() != len || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of the proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01T00:00:00Z
+    const NEW_YEAR_UTC: i64 = 1_704_067_200;
+
+    #[test]
+    fn times_are_in_jst() {
+        assert_eq!(
+            parse_published_at("2024-01-01"),
+            Some(NEW_YEAR_UTC - JST_OFFSET_SECS)
+        );
+        assert_eq!(
+            parse_published_at("2023-12-31 23:59"),
+            Some(NEW_YEAR_UTC - JST_OFFSET_SECS - 60)
+        );
+        assert_eq!(parse_published_at("2024-01-01 09:00"), Some(NEW_YEAR_UTC));
+        assert_eq!(
+            parse_published_at(" 2024-01-01 08:59 "),
+            Some(NEW_YEAR_UTC - 60)
+        );
+    }
+
+    #[test]
+    fn leap_days() {
+        assert_eq!(
+            parse_published_at("2024-02-29"),
+            Some(1_709_164_800 - JST_OFFSET_SECS)
+        );
+        assert_eq!(
+            parse_published_at("2000-02-29"),
+            Some(951_782_400 - JST_OFFSET_SECS)
+        );
+        for date in ["2023-02-29", "1900-02-29", "2100-02-29"] {
+            assert_eq!(parse_published_at(date), None, "{}", date);
+        }
+    }
+
+    #[test]
+    fn invalid_dates() {
+        for published_at in [
+            "",
+            "2025-02-30",
+            "2025-04-31",
+            "2025-13-01",
+            "2025-00-10",
+            "2025-01-00",
+            "2025-1-01",
+            "25-01-01",
+            "2025/01/01",
+            "2025-01-01-01",
+            "2025-01-01T09:00",
+            "2025-01-01 9:00",
+            "2025-01-01 24:00",
+            "2025-01-01 12:60",
+            "2025-01-01 12",
+            "+025-01-01",
+        ] {
+            assert_eq!(parse_published_at(published_at), None, "{}", published_at);
+        }
+    }
+
+    #[test]
+    fn due_from_the_minute() {
+        assert!(!is_due_at("2024-01-01 09:00", NEW_YEAR_UTC - 1));
+        assert!(is_due_at("2024-01-01 09:00", NEW_YEAR_UTC));
+        assert!(is_due_at("2024-01-01 09:00", NEW_YEAR_UTC + 1));
+        assert!(!is_due_at(
+            "2024-02-30 09:00",
+            NEW_YEAR_UTC + 365 * 24 * 60 * 60
+        ));
+    }
+}
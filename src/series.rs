@@ -0,0 +1,229 @@
+use crate::{
+    article,
+    ast::{Element, ZetaFrontmatter},
+    r#macro::Platform,
+};
+
+/// Label of the link to the previous part.
+const PREVIOUS_LABEL: &str = "前の記事: ";
+/// Label of the link to the next part.
+const NEXT_LABEL: &str = "次の記事: ";
+
+pub struct Part {
+    pub slug: String,
+    pub title: String,
+    pub qiita_id: Option<String>,
+}
+
+/// Frontmatters of the articles, read once to find the parts of a series.
+#[derive(Default)]
+pub struct Index {
+    /// `(slug, frontmatter)` of every article in `zeta/`
+    articles: Vec<(String, ZetaFrontmatter)>,
+}
+
+impl Index {
+    /// Reads the frontmatters of the articles in `zeta/`.
+    pub fn read() -> Self {
+        let articles = article::slugs()
+            .into_iter()
+            .filter_map(|slug| {
+                let frontmatter = article::read_zeta_frontmatter(&slug)?;
+                Some((slug, frontmatter))
+            })
+            .collect();
+        Self { articles }
+    }
+
+    /// Builds the navigation block appended to every part of a series.
+    pub fn navigation(
+        &self,
+        slug: &str,
+        frontmatter: &ZetaFrontmatter,
+        platform: Platform,
+    ) -> Vec<Element> {
+        let Some(series) = &frontmatter.series else {
+            return Vec::new();
+        };
+        let parts = self.parts(&series.name, &platform);
+        parts_navigation(&series.name, &parts, slug, platform)
+    }
+
+    /// Collects the articles of a series published on the platform, in order.
+    fn parts(&self, name: &str, platform: &Platform) -> Vec<Part> {
+        let mut parts: Vec<(usize, Part)> = self
+            .articles
+            .iter()
+            .filter_map(|(slug, frontmatter)| {
+                let order = frontmatter
+                    .series
+                    .as_ref()
+                    .filter(|series| series.name == name)?
+                    .order;
+                if frontmatter
+                    .only
+                    .as_ref()
+                    .is_some_and(|only| only != platform)
+                {
+                    return None;
+                }
+
+                let frontmatter = match platform {
+                    Platform::Zenn => frontmatter.clone().for_zenn(),
+                    Platform::Qiita => frontmatter.clone().for_qiita(),
+                };
+                let qiita_id = article::resolve_qiita_id(slug, &frontmatter);
+                Some((
+                    order,
+                    Part {
+                        slug: slug.clone(),
+                        title: frontmatter.title,
+                        qiita_id,
+                    },
+                ))
+            })
+            .collect();
+        parts.sort_by(|(a_order, a), (b_order, b)| (a_order, &a.slug).cmp(&(b_order, &b.slug)));
+
+        parts.into_iter().map(|(_, part)| part).collect()
+    }
+}
+
+/// Builds the navigation block of the part `slug` among `parts` in reading order, e.g. a book chapter.
+pub fn parts_navigation(name: &str, parts: &[Part], slug: &str, platform: Platform) -> Vec<Element> {
+    let Some(current) = parts.iter().position(|part| part.slug == slug) else {
+        return Vec::new();
+    };
+
+    let mut elements = vec![Element::Text(format!("\n---\n\n**{}**\n\n", name))];
+    if current > 0 {
+        elements.push(Element::Text(PREVIOUS_LABEL.to_string()));
+        elements.push(link(&parts[current - 1], &platform));
+        elements.push(Element::Text("\n".to_string()));
+    }
+    if let Some(next) = parts.get(current + 1) {
+        elements.push(Element::Text(NEXT_LABEL.to_string()));
+        elements.push(link(next, &platform));
+        elements.push(Element::Text("\n".to_string()));
+    }
+    elements.push(Element::Text("\n".to_string()));
+
+    for (index, part) in parts.iter().enumerate() {
+        elements.push(Element::Text(format!("{}. ", index + 1)));
+        if index == current {
+            elements.push(Element::Text(format!("**{}**", part.title)));
+        } else {
+            elements.push(link(part, &platform));
+        }
+        elements.push(Element::Text("\n".to_string()));
+    }
+
+    elements
+}
+
+/// Links to a part, or shows only its title while it has no Qiita id yet.
+fn link(part: &Part, platform: &Platform) -> Element {
+    if *platform == Platform::Qiita && part.qiita_id.is_none() {
+        return Element::Text(part.title.clone());
+    }
+    Element::ArticleLink {
+        text: vec![Element::Text(part.title.clone())],
+        slug: part.slug.clone(),
+        qiita_id: part.qiita_id.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Series;
+
+    fn article(slug: &str, order: usize, only: Option<Platform>) -> (String, ZetaFrontmatter) {
+        let frontmatter = ZetaFrontmatter {
+            title: slug.to_uppercase(),
+            qiita_id: (slug != "c").then(|| format!("{}-id", slug)),
+            only,
+            series: Some(Series {
+                name: "S".to_string(),
+                order,
+            }),
+            ..ZetaFrontmatter::default()
+        };
+        (slug.to_string(), frontmatter)
+    }
+
+    /// Renders the navigation with links as `[title](slug)`.
+    fn navigation(index: &Index, slug: &str, platform: Platform) -> String {
+        let frontmatter = &index
+            .articles
+            .iter()
+            .find(|(article, _)| article == slug)
+            .unwrap()
+            .1;
+        index
+            .navigation(slug, frontmatter, platform)
+            .iter()
+            .map(|element| match element {
+                Element::Text(text) => text.clone(),
+                Element::ArticleLink { slug, .. } => format!("[{}]({})", slug.to_uppercase(), slug),
+                element => panic!("unexpected {:?}", element),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parts_are_in_order() {
+        let index = Index {
+            articles: vec![
+                article("c", 2, None),
+                article("b", 1, None),
+                article("a", 1, None),
+                article("q", 0, Some(Platform::Qiita)),
+            ],
+        };
+
+        assert_eq!(
+            navigation(&index, "b", Platform::Zenn),
+            format!(
+                "\n---\n\n**S**\n\n{}[A](a)\n{}[C](c)\n\n1. [A](a)\n2. **B**\n3. [C](c)\n",
+                PREVIOUS_LABEL, NEXT_LABEL
+            )
+        );
+        assert_eq!(
+            navigation(&index, "a", Platform::Zenn),
+            format!(
+                "\n---\n\n**S**\n\n{}[B](b)\n\n1. **A**\n2. [B](b)\n3. [C](c)\n",
+                NEXT_LABEL
+            )
+        );
+        // parts without a Qiita id aren't linked yet
+        assert_eq!(
+            navigation(&index, "q", Platform::Qiita),
+            format!(
+                "\n---\n\n**S**\n\n{}[A](a)\n\n1. **Q**\n2. [A](a)\n3. [B](b)\n4. C\n",
+                NEXT_LABEL
+            )
+        );
+        assert_eq!(
+            navigation(&index, "c", Platform::Qiita),
+            format!(
+                "\n---\n\n**S**\n\n{}[B](b)\n\n1. [Q](q)\n2. [A](a)\n3. [B](b)\n4. **C**\n",
+                PREVIOUS_LABEL
+            )
+        );
+        assert_eq!(navigation(&index, "q", Platform::Zenn), "");
+    }
+
+    #[test]
+    fn a_series_of_one_article() {
+        let mut only = article("x", 0, None);
+        only.1.series.as_mut().unwrap().name = "other".to_string();
+        let index = Index {
+            articles: vec![article("a", 1, None), only],
+        };
+        assert_eq!(
+            navigation(&index, "x", Platform::Zenn),
+            "\n---\n\n**other**\n\n\n1. **X**\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use crate::{ast::Element, r#macro::Platform};
+
+/// Collects the headings compiled for the platform as `(level, elements)`.
+pub fn collect_headings(elements: &[Element], platform: &Platform) -> Vec<(usize, Vec<Element>)> {
+    let mut headings = Vec::new();
+    collect(elements, platform, &mut headings);
+    headings
+}
+
+fn collect(elements: &[Element], platform: &Platform, headings: &mut Vec<(usize, Vec<Element>)>) {
+    for element in elements {
+        match element {
+            Element::Heading { level, text } => headings.push((*level, text.clone())),
+            Element::Conditional { platforms, body } if platforms.contains(platform) => {
+                collect(body, platform, headings)
+            }
+            Element::Macro(macro_info) => match platform {
+                Platform::Zenn => collect(&macro_info.zenn, platform, headings),
+                Platform::Qiita => collect(&macro_info.qiita, platform, headings),
+            },
+            // nested headings get anchors too, so they count for the suffixes of duplicates
+            Element::Message { body, .. } | Element::Details { body, .. } => {
+                collect(body, platform, headings)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Renders a nested list of links to the headings.
+pub fn render(headings: &[(usize, String)], anchor: fn(&str) -> String) -> String {
+    let Some(base) = headings.iter().map(|(level, _)| *level).min() else {
+        return String::new();
+    };
+
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut result = String::new();
+    for (level, text) in headings {
+        let mut id = anchor(&text.replace(['*', '`'], ""));
+        let count = used.entry(id.clone()).or_insert(0);
+        if *count > 0 {
+            id = format!("{}-{}", id, count);
+        }
+        *count += 1;
+
+        result.push_str(&format!(
+            "{}- [{}](#{})\n",
+            "  ".repeat(level - base),
+            text,
+            id
+        ));
+    }
+    result
+}
+
+/// markdown-it-anchor as configured by Zenn: `encodeURIComponent` of the lowercased text.
+pub fn zenn_anchor(text: &str) -> String {
+    let slug = text
+        .trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+
+    let mut result = String::new();
+    for c in slug.chars() {
+        if c.is_ascii_alphanumeric() || "-_.!~*'()".contains(c) {
+            result.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                result.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    result
+}
+
+/// Qiita keeps word characters, hyphens and spaces, then replaces spaces with hyphens.
+pub fn qiita_anchor(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-' || *c == ' ')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{MarkdownDoc, ZetaFrontmatter},
+        parser::Parser,
+        scanner::Scanner,
+    };
+
+    #[test]
+    fn zenn_anchors() {
+        for (text, anchor) in [
+            ("Hello World", "hello-world"),
+            ("  a \t b  ", "a-b"),
+            ("はじめに", "%E3%81%AF%E3%81%98%E3%82%81%E3%81%AB"),
+            ("What's new? (v1.0)", "what's-new%3F-(v1.0)"),
+            ("C++ & Rust", "c%2B%2B-%26-rust"),
+            ("snake_case!~*", "snake_case!~*"),
+        ] {
+            assert_eq!(zenn_anchor(text), anchor, "{}", text);
+        }
+    }
+
+    #[test]
+    fn qiita_anchors() {
+        for (text, anchor) in [
+            ("Hello World", "hello-world"),
+            ("はじめに", "はじめに"),
+            ("Rust 1.70の新機能！", "rust-170の新機能"),
+            ("What's new? (v1.0)", "whats-new-v10"),
+            ("C++ & Rust", "c--rust"),
+            ("「設定」について", "設定について"),
+            ("snake_case-name", "snake_case-name"),
+        ] {
+            assert_eq!(qiita_anchor(text), anchor, "{}", text);
+        }
+    }
+
+    #[test]
+    fn duplicates_are_numbered() {
+        let headings = [
+            (2, "概要".to_string()),
+            (3, "概要".to_string()),
+            (2, "**概要**".to_string()),
+            (2, "`Code`".to_string()),
+        ];
+        assert_eq!(
+            render(&headings, zenn_anchor),
+            "- [概要](#%E6%A6%82%E8%A6%81)\n  \
+             - [概要](#%E6%A6%82%E8%A6%81-1)\n\
+             - [**概要**](#%E6%A6%82%E8%A6%81-2)\n\
+             - [`Code`](#code)\n"
+        );
+        assert_eq!(
+            render(&headings, qiita_anchor),
+            "- [概要](#概要)\n  - [概要](#概要-1)\n- [**概要**](#概要-2)\n- [`Code`](#code)\n"
+        );
+    }
+
+    #[test]
+    fn nested_headings() {
+        let source = "## a\n\n```md\n## code\n```\n\n:::details d\n## details\n:::\n\n\
+            <only qiita>\n\n## qiita\n\n</only>\n";
+        let tokens = Scanner::new(source.chars().collect())
+            .scan_snippet()
+            .unwrap();
+        let file = Parser::new(MarkdownDoc::new(String::new(), tokens))
+            .parse_with(ZetaFrontmatter::default())
+            .unwrap();
+        let texts = |platform| {
+            collect_headings(&file.elements, &platform)
+                .into_iter()
+                .map(|(_, text)| match &text[..] {
+                    [Element::Trivia(_), Element::Text(text)] => text.clone(),
+                    text => panic!("unexpected {:?}", text),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(Platform::Zenn), ["a", "details"]);
+        assert_eq!(texts(Platform::Qiita), ["a", "details", "qiita"]);
+    }
+}
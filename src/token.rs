@@ -1,10 +1,12 @@
-use crate::r#macro::TokenizedMacro;
+use crate::r#macro::{Platform, TokenizedMacro};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub token_type: TokenType,
     pub row: usize,
     pub col: usize,
+    /// the token as written
+    pub source: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,11 +24,25 @@ pub enum TokenType {
     Image {
         alt: String,
         url: String,
+        /// `=250x`
+        width: Option<usize>,
+        /// `*caption*` under the image
+        caption: Option<String>,
+    },
+    /// [text](zeta:other-article)
+    ArticleLink {
+        text: Vec<Token>,
+        slug: String,
     },
     /// inline footnote
-    InlineFootnote(String),
+    InlineFootnote(Vec<Token>),
     /// footnote
     Footnote(String),
+    /// [^name]: footnote definition
+    FootnoteDefinition {
+        name: String,
+        body: Vec<Token>,
+    },
     /// :::message
     MessageBegin {
         level: usize,
@@ -37,9 +53,38 @@ pub enum TokenType {
         level: usize,
         title: String,
     },
+    /// :::only
+    ConditionalBegin {
+        level: usize,
+        platforms: Vec<Platform>,
+    },
+    /// <only>...</only>
+    Conditional {
+        platforms: Vec<Platform>,
+        body: Vec<Token>,
+    },
+    /// source text that only carries layout, like the `## ` of a heading
+    Trivia(String),
+    /// `## text`
+    Heading {
+        level: usize,
+        text: Vec<Token>,
+    },
+    /// <toc/>
+    Toc,
+    /// :::slide page separator
+    SlideBreak {
+        title: Option<String>,
+    },
     /// :::
     MessageOrDetailsEnd {
         level: usize,
     },
     Macro(TokenizedMacro),
+    /// {{name}}
+    MacroReference(String),
+    /// <include path="..."/>
+    Include(String),
+    /// ```mermaid
+    Mermaid(String),
 }